@@ -1,11 +1,16 @@
 use clap::Parser;
 use gradia_core::{
-    parser::{parse, tokenize},
+    expr::GradiaError,
+    macros::expand,
+    module::{program, FileLoader},
+    parser::read_recovering,
     std::builtin_function,
+    typecheck::Checker,
     types::Scope,
 };
 use rustyline::DefaultEditor;
 use std::fs::read_to_string;
+use std::process::exit;
 
 const VERSION: &str = "0.1.0";
 
@@ -27,29 +32,20 @@ struct Cli {
 }
 
 fn main() {
-    let mut scope: Scope = builtin_function();
     let args = Cli::parse();
 
+    // Imports are relative to the script file, or to the current directory
+    let mut scope: Scope = program(builtin_function(), FileLoader, args.file.clone());
+    let mut checker = Checker::for_program(&scope);
+
     if let Some(path) = args.file {
-        if let Ok(code) = read_to_string(path) {
-            if let Ok(lines) = tokenize(code) {
-                for line in lines {
-                    if let Ok(ast) = parse(line) {
-                        ast.eval(&mut scope).unwrap();
-                    }
-                }
-            }
+        if let Ok(code) = read_to_string(&path) {
+            run(&path, code, &mut scope, &mut checker);
         } else {
             eprintln!("Error! opening file is fault");
         }
     } else if let Some(code) = args.one_liner {
-        if let Ok(lines) = tokenize(code) {
-            for line in lines {
-                if let Ok(ast) = parse(line) {
-                    ast.eval(&mut scope).unwrap();
-                }
-            }
-        }
+        run("<one-liner>", code, &mut scope, &mut checker);
     } else {
         println!("Gradia {VERSION}");
         if let Ok(mut rl) = DefaultEditor::new() {
@@ -57,19 +53,32 @@ fn main() {
                 match rl.readline("> ") {
                     Ok(code) => {
                         rl.add_history_entry(&code).unwrap_or_default();
-                        match tokenize(code) {
-                            Ok(lines) => {
-                                for line in lines {
-                                    match parse(line) {
-                                        Ok(ast) => match ast.eval(&mut scope) {
-                                            Ok(result) => println!("{:?}", result),
-                                            Err(err) => println!("{err}"),
-                                        },
-                                        Err(err) => println!("{err}"),
-                                    }
+                        let (program, errors) = read_recovering(&code);
+                        if !errors.is_empty() {
+                            for err in errors {
+                                println!("{}", err.report("<repl>", &code));
+                            }
+                            continue;
+                        }
+                        for ast in program {
+                            let ast = match expand(&ast, &mut scope) {
+                                Ok(ast) => ast,
+                                Err(err) => {
+                                    println!("{}", err.report("<repl>", &code));
+                                    continue;
                                 }
+                            };
+                            let errors = checker.check(std::slice::from_ref(&ast));
+                            if !errors.is_empty() {
+                                for err in errors {
+                                    println!("{}", err.report("<repl>", &code));
+                                }
+                                continue;
+                            }
+                            match ast.eval(&mut scope) {
+                                Ok(result) => println!("{:?}", result),
+                                Err(err) => println!("{}", err.report("<repl>", &code)),
                             }
-                            Err(err) => println!("{err}"),
                         }
                     }
                     Err(err) => println!("{err}"),
@@ -78,3 +87,38 @@ fn main() {
         }
     }
 }
+
+/// Parse whole code, check its types statically and then evaluate it
+fn run(file: &str, code: String, scope: &mut Scope, checker: &mut Checker) {
+    let fail = |err: GradiaError| -> ! {
+        eprintln!("{}", err.report(file, &code));
+        exit(1)
+    };
+
+    // All syntax errors are reported before anything runs
+    let (program, errors) = read_recovering(&code);
+    if !errors.is_empty() {
+        for err in errors {
+            eprintln!("{}", err.report(file, &code));
+        }
+        exit(1);
+    }
+
+    // Macros are expanded in order, so that each can be used after its definition
+    let program: Vec<_> = program
+        .iter()
+        .map(|i| expand(i, scope).unwrap_or_else(|err| fail(err)))
+        .collect();
+
+    let errors = checker.check(&program);
+    if !errors.is_empty() {
+        for err in errors {
+            eprintln!("{}", err.report(file, &code));
+        }
+        exit(1);
+    }
+
+    for ast in program {
+        ast.eval(scope).unwrap_or_else(|err| fail(err));
+    }
+}
@@ -0,0 +1,63 @@
+use gradia_core::{
+    fraction::Fraction,
+    parser::{parse, tokenize},
+    std::builtin_function,
+};
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+/// Run the function and return the average time of the runs
+fn measure(runs: u32, mut f: impl FnMut()) -> Duration {
+    let start = Instant::now();
+    for _ in 0..runs {
+        f();
+    }
+    start.elapsed() / runs
+}
+
+fn main() {
+    // Floats that aren't whole numbers, as computed by `%`, `^` and `range` steps
+    let floats: Vec<f64> = (1..=1000).map(|i| i as f64 / 7.0 + 0.1).collect();
+    println!(
+        "{:<32} {:?}",
+        "convert 1k floats",
+        measure(5, || {
+            for i in &floats {
+                black_box(Fraction::try_new(*i));
+            }
+        })
+    );
+
+    // Script that has thousands of decimal literals
+    let literals = (1..=3000)
+        .map(|i| format!("{i}.{:03}", i * 7 % 1000))
+        .collect::<Vec<String>>()
+        .join(" ");
+    let code = format!("(+ {literals})");
+    println!(
+        "{:<32} {:?}",
+        "parse 3k decimal literals",
+        measure(5, || {
+            for line in tokenize(code.clone()).unwrap() {
+                black_box(parse(line).unwrap());
+            }
+        })
+    );
+
+    let code = "(reduce (range 0 100 0.1) (lambda (a b) (+ a (% b 3))))";
+    let program: Vec<_> = tokenize(code.to_string())
+        .unwrap()
+        .into_iter()
+        .map(|i| parse(i).unwrap())
+        .collect();
+    println!(
+        "{:<32} {:?}",
+        "fractional range and modulo",
+        measure(5, || {
+            let mut scope = builtin_function();
+            for line in &program {
+                black_box(line.eval(&mut scope).unwrap());
+            }
+        })
+    );
+}
@@ -0,0 +1,30 @@
+use gradia_core::parser::read;
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+/// Parse all forms of the code in one pass, and return the average time of the runs
+fn measure(code: &str, runs: u32) -> Duration {
+    let start = Instant::now();
+    for _ in 0..runs {
+        black_box(read(code).unwrap());
+    }
+    start.elapsed() / runs
+}
+
+fn main() {
+    // Nested expression like `(+ 1 (+ 1 ... 0))`
+    let depth = 1000;
+    let nested = format!("{}0{}", "(+ 1 ".repeat(depth), ")".repeat(depth));
+
+    // Many small forms in one file
+    let wide =
+        "(define (square x:number):number (* x x)) (print (square 12) \"done\")\n".repeat(1000);
+
+    let benches = [
+        ("1000-level nested expression", nested),
+        ("2k top-level forms", wide),
+    ];
+    for (name, code) in benches {
+        println!("{name:<32} {:?}", measure(&code, 5));
+    }
+}
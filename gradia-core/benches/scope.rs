@@ -0,0 +1,41 @@
+use gradia_core::{
+    parser::{parse, tokenize},
+    std::builtin_function,
+};
+use std::time::{Duration, Instant};
+
+/// Evaluate the code and return the average time of the runs
+fn measure(code: &str, runs: u32) -> Duration {
+    let mut total = Duration::ZERO;
+    for _ in 0..runs {
+        let mut scope = builtin_function();
+        let program: Vec<_> = tokenize(code.to_string())
+            .unwrap()
+            .into_iter()
+            .map(|i| parse(i).unwrap())
+            .collect();
+
+        let start = Instant::now();
+        for line in program {
+            line.eval(&mut scope).unwrap();
+        }
+        total += start.elapsed();
+    }
+    total / runs
+}
+
+fn main() {
+    let benches = [
+        (
+            "recursive fib 20",
+            "(define (fib n) (if (< n 2) n (+ (fib (- n 1)) (fib (- n 2))))) (fib 20)",
+        ),
+        (
+            "reduce over 10k-element list",
+            "(reduce (range 10000) (lambda (a b) (+ a b)))",
+        ),
+    ];
+    for (name, code) in benches {
+        println!("{name:<32} {:?}", measure(code, 5));
+    }
+}
@@ -1,121 +1,349 @@
-use crate::types::{Class, Function, Scope, Type};
+use crate::fraction::Fraction;
+use crate::macros;
+use crate::module;
+use crate::types::{Class, ErrorValue, Flow, Function, Lambda, Scope, Type};
+use std::cell::RefCell;
 use std::fmt::{self, Debug};
+use std::rc::Rc;
 use thiserror::Error;
 
+thread_local! {
+    // Locations of the calls that the error being raised went out of, `try` takes them for its error value
+    static UNWOUND: RefCell<Vec<Span>> = const { RefCell::new(Vec::new()) };
+}
+
 #[derive(Debug, Error)]
 pub enum GradiaError {
     #[error("Runtime Error! {0}")]
-    Runtime(String),
+    Runtime(String, Option<Span>),
 
-    #[error("Function Error! the passed arguments length {0} is different to expected length {1} of the function's arguments")]
-    Function(usize, usize),
+    #[error("Function Error! {}", self.message())]
+    Function(usize, usize, Option<Span>),
 
-    #[error("Type Error! the result value `{0:?}` is different to expected type `{1}`")]
-    Type(Type, String),
+    #[error("Type Error! {}", self.message())]
+    Type(Type, String, Option<Span>),
 
     #[error("Syntax Error! {0}")]
-    Syntax(String),
+    Syntax(String, Option<Span>),
+
+    /// Error value thrown by `raise` or the one caught by `catch` and thrown again
+    #[error("{} Error! {}", .0.title(), .0.message)]
+    Raised(Rc<ErrorValue>),
+}
+
+/// Location of source code, `start` and `end` are byte offsets and `line` and `column` count from 1.
+/// `file` is the imported module that the code is in, and 0 is the code given to the host
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+    pub line: usize,
+    pub column: usize,
+    pub file: usize,
 }
 
 #[derive(Clone)]
 pub struct Expr {
     pub expr: Type,
     pub annotate: Option<Class>,
+    pub span: Option<Span>,
 }
 
-impl Expr {
-    pub fn eval(&self, scope: &mut Scope) -> Result<Type, GradiaError> {
-        let result = if let Type::Expr(expr) = &self.expr {
-            // Prepare expression
-            let expr = {
-                let mut new = vec![];
-                for i in expr {
-                    new.push(i.eval(scope)?)
-                }
-                new
-            };
+impl GradiaError {
+    pub fn span(&self) -> Option<Span> {
+        match self {
+            GradiaError::Runtime(_, span)
+            | GradiaError::Function(_, _, span)
+            | GradiaError::Type(_, _, span)
+            | GradiaError::Syntax(_, span) => *span,
+            GradiaError::Raised(error) => error.span,
+        }
+    }
+
+    /// Keyword of the kind of the error like `:type`
+    pub fn kind(&self) -> String {
+        match self {
+            GradiaError::Runtime(_, _) => ":runtime".to_string(),
+            GradiaError::Function(_, _, _) => ":function".to_string(),
+            GradiaError::Type(_, _, _) => ":type".to_string(),
+            GradiaError::Syntax(_, _) => ":syntax".to_string(),
+            GradiaError::Raised(error) => error.kind.clone(),
+        }
+    }
+
+    /// Message of the error without its kind
+    pub fn message(&self) -> String {
+        match self {
+            GradiaError::Runtime(message, _) | GradiaError::Syntax(message, _) => message.clone(),
+            GradiaError::Function(given, expected, _) => format!(
+                "the passed arguments length {given} is different to expected length {expected} of the function's arguments"
+            ),
+            GradiaError::Type(value, expected, _) => {
+                format!("the result value `{value:?}` is different to expected {expected}")
+            }
+            GradiaError::Raised(error) => error.message.clone(),
+        }
+    }
+
+    /// Error as the value, the payload is the value in question like the one of type error
+    pub fn into_value(self, stack: Vec<Span>) -> Rc<ErrorValue> {
+        let payload = match &self {
+            // Error raised again keeps the calls from where it was raised first
+            GradiaError::Raised(error) if !error.stack.is_empty() => return error.clone(),
+            GradiaError::Raised(error) => {
+                return Rc::new(ErrorValue {
+                    stack,
+                    ..(**error).clone()
+                })
+            }
+            // Lengths of the passed and expected arguments
+            GradiaError::Function(given, expected, _) => Type::List(
+                [given, expected]
+                    .iter()
+                    .map(|i| Expr {
+                        expr: Type::Number(Fraction::new(**i as f64)),
+                        annotate: None,
+                        span: None,
+                    })
+                    .collect(),
+            ),
+            GradiaError::Type(value, _, _) => value.clone(),
+            GradiaError::Runtime(_, _) | GradiaError::Syntax(_, _) => Type::Null,
+        };
+        Rc::new(ErrorValue {
+            kind: self.kind(),
+            message: self.message(),
+            payload,
+            span: self.span(),
+            stack,
+        })
+    }
 
-            if let Some(Type::Function(Function::BuiltIn(func))) = expr.get(0).cloned() {
-                func(expr[1..].to_vec(), scope)?
-            } else if let Some(Type::Function(Function::UserDefined(args, code))) =
-                expr.get(0).cloned()
-            {
-                // Check arguments length
-                if args.len() != expr.get(1..).unwrap_or_default().len() {
-                    return Err(GradiaError::Function(
-                        expr.get(1..).unwrap_or_default().len(),
-                        args.len(),
-                    ));
+    /// Set where the error occurred, unless more inner location is already known
+    pub fn at(mut self, location: Option<Span>) -> Self {
+        match &mut self {
+            GradiaError::Runtime(_, span)
+            | GradiaError::Function(_, _, span)
+            | GradiaError::Type(_, _, span)
+            | GradiaError::Syntax(_, span) => {
+                if span.is_none() {
+                    *span = location;
                 }
+            }
+            GradiaError::Raised(error) => {
+                if error.span.is_none() {
+                    Rc::make_mut(error).span = location;
+                }
+            }
+        }
+        self
+    }
+
+    /// Format error message with its location and caret under the offending code
+    /// Error message with the line of the code, error in imported module is shown with the source of the module
+    pub fn report(&self, file: &str, source: &str) -> String {
+        let Some(span) = self.span() else {
+            return format!("{file}: {self}");
+        };
+        if let Some((file, source)) = module::source(span.file) {
+            return self.render(&file, &source, span);
+        }
+        self.render(file, source, span)
+    }
+
+    fn render(&self, file: &str, source: &str, span: Span) -> String {
+        let line = source.lines().nth(span.line - 1).unwrap_or_default();
+        let width = source
+            .get(span.start..span.end)
+            .unwrap_or_default()
+            .lines()
+            .next()
+            .unwrap_or_default()
+            .chars()
+            .count()
+            .max(1);
+        let number = span.line.to_string();
+        let margin = " ".repeat(number.len());
+        format!(
+            "{file}:{}:{}: {self}\n{margin} |\n{number} | {line}\n{margin} | {}{}",
+            span.line,
+            span.column,
+            " ".repeat(span.column - 1),
+            "^".repeat(width)
+        )
+    }
+}
 
-                // Setting arguemnt and its value
-                let mut func_scope = scope.clone();
-                for (k, v) in args.iter().zip(expr.get(1..).unwrap_or_default().to_vec()) {
-                    if let Some(annotate) = k.annotate.clone() {
-                        // Type check between arguments and expects
-                        if annotate.get_type() == v.get_type() {
-                            // Setting argument by passed value
-                            func_scope.insert(k.expr.get_string(), v);
-                        } else {
-                            return Err(GradiaError::Type(v, annotate.get_type()));
+/// Take the locations of the calls that the error being raised went out of, the innermost first
+pub fn take_unwound() -> Vec<Span> {
+    UNWOUND.with(|i| i.take())
+}
+
+/// Put back the calls of the error that is raised again after the cleanup code
+pub fn restore_unwound(stack: Vec<Span>) {
+    UNWOUND.with(|i| *i.borrow_mut() = stack);
+}
+
+/// Evaluation that may be left to be continued, so that calls in tail position don't grow the stack
+enum Tail {
+    Value(Type),
+    // User-defined function with its arguments bound, and the location of the call
+    Call(Rc<Lambda>, Scope, Option<Span>),
+}
+
+impl Tail {
+    /// Run the pending function calls until the value is determined.
+    /// Error records the calls that it goes out of, the innermost first
+    fn resolve(self) -> Result<Type, GradiaError> {
+        let mut frames = vec![];
+        self.run(&mut frames).inspect_err(|_| {
+            UNWOUND.with(|i| i.borrow_mut().extend(frames.iter().rev()));
+        })
+    }
+
+    fn run(self, frames: &mut Vec<Span>) -> Result<Type, GradiaError> {
+        let mut tail = self;
+
+        // Return types of the functions that return this value, with their returning code and definition
+        let mut returns: Vec<(Class, Option<Span>, Scope)> = vec![];
+        loop {
+            match tail {
+                Tail::Value(mut result) => {
+                    for (class, span, scope) in returns.into_iter().rev() {
+                        result = class.check(result, &scope).map_err(|err| err.at(span))?;
+                    }
+                    return Ok(result);
+                }
+                Tail::Call(lambda, mut scope, span) => {
+                    // Recursion in tail position from the same call is recorded once
+                    if let Some(span) = span.filter(|i| frames.last() != Some(i)) {
+                        frames.push(span);
+                    }
+                    let Some((last, lines)) = lambda.body.split_last() else {
+                        tail = Tail::Value(Type::Null);
+                        continue;
+                    };
+                    // Each return type is checked once, so tail calls that go back and forth
+                    // between functions keep the pending checks bounded
+                    if let Some(result) = &lambda.result {
+                        if returns
+                            .iter()
+                            .all(|(class, _, _)| class.get_type() != result.get_type())
+                        {
+                            returns.push((result.clone(), last.span, lambda.scope.clone()));
                         }
-                    } else {
-                        // Setting argument by passed value
-                        func_scope.insert(k.expr.get_string(), v);
                     }
+                    for line in lines {
+                        line.eval(&mut scope)?;
+                    }
+                    tail = last.eval_tail(&mut scope)?;
+                    // Function is released before the frame, so that the frame with functions defined in it can be freed
+                    drop(lambda);
                 }
+            }
+        }
+    }
+}
 
-                // Execution of function's code
-                let mut result = Type::Null;
-                for line in code {
-                    result = Expr {
-                        // Convert list to as expression
-                        expr: if let Type::List(expr) = line.to_owned() {
-                            Type::Expr(expr)
-                        } else {
-                            line.to_owned()
-                        },
-                        annotate: None,
-                    }
-                    .eval(&mut func_scope)?
+impl Expr {
+    /// Code inside of the clause like `(catch name code...)`
+    pub fn clause(&self, name: &str) -> Option<&[Expr]> {
+        match &self.expr {
+            Type::Expr(list) => match list.split_first() {
+                Some((head, rest)) if matches!(&head.expr, Type::Symbol(i) if i == name) => {
+                    Some(rest)
+                }
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    pub fn eval(&self, scope: &mut Scope) -> Result<Type, GradiaError> {
+        self.eval_tail(scope)?.resolve()
+    }
+
+    fn eval_tail(&self, scope: &mut Scope) -> Result<Tail, GradiaError> {
+        let result = self.eval_expr(scope).map_err(|err| err.at(self.span))?;
+
+        // Type check between result value and except type
+        if let Some(annotate) = &self.annotate {
+            let result = result.resolve()?;
+            Ok(Tail::Value(
+                annotate
+                    .check(result, scope)
+                    .map_err(|err| err.at(self.span))?,
+            ))
+        } else {
+            Ok(result)
+        }
+    }
+
+    fn eval_expr(&self, scope: &mut Scope) -> Result<Tail, GradiaError> {
+        Ok(if let Type::Expr(expr) = &self.expr {
+            let (function, sources) = match expr.split_first() {
+                Some((head, sources)) => (head.eval(scope)?, sources),
+                None => (Type::Null, &[][..]),
+            };
+
+            // Macro that isn't expanded beforehand is expanded when it's called
+            if let Type::Function(Function::Macro(lambda)) = &function {
+                let code = macros::call(lambda, sources, self.span)?;
+                return macros::expand(&code, scope)?.eval_tail(scope);
+            }
+
+            // Special forms take their arguments without evaluation
+            if let Type::Function(Function::Special(form)) = function {
+                match form(sources, scope)? {
+                    Flow::Value(result) => Tail::Value(result),
+                    Flow::Tail(expr, mut scope) => expr.eval_tail(&mut scope)?,
                 }
-                result
             } else {
-                return Err(GradiaError::Syntax(format!(
-                    "first atom in expression should be function, but provided `{:?}` is not function",
-                    expr.get(0).cloned().unwrap_or_default()
-                )));
+                // Prepare expression
+                let mut params = vec![];
+                for i in sources {
+                    params.push(i.eval(scope)?)
+                }
+
+                match function {
+                    Type::Function(Function::BuiltIn(func)) => Tail::Value(func(params, scope)?),
+                    Type::Function(Function::Native(native)) => Tail::Value((native.call)(params)?),
+                    Type::Function(Function::UserDefined(lambda)) => {
+                        // Arguments are bound in new frame on the scope where the function was created
+                        let func_scope = lambda.bind(params, sources)?;
+
+                        // Execution of function's code is left to the caller
+                        Tail::Call(lambda, func_scope, self.span)
+                    }
+                    other => {
+                        return Err(GradiaError::Syntax(
+                            format!(
+                                "first atom in expression should be function, but provided `{other:?}` is not function"
+                            ),
+                            expr.first().and_then(|i| i.span),
+                        ));
+                    }
+                }
             }
         } else {
             let expr = self.expr.clone();
-            if let Type::Symbol(name) = expr.clone() {
+            Tail::Value(if let Type::Symbol(name) = expr.clone() {
                 // Loading variable from scope
-                if let Some(value) = scope.get(&name).to_owned() {
-                    value.to_owned()
+                if let Some(value) = scope.get(&name) {
+                    value
                 } else {
                     expr
                 }
             } else {
                 expr
-            }
-        };
-
-        // Type check between result value and except type
-        if let Some(annotate) = self.annotate.clone() {
-            if &result.get_type() == &annotate.get_type() {
-                Ok(result)
-            } else {
-                return Err(GradiaError::Type(result, annotate.get_type()));
-            }
-        } else {
-            Ok(result)
-        }
+            })
+        })
     }
 }
 
 impl Debug for Expr {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        if let Some(annotate) = self.annotate.clone() {
+        if let Some(annotate) = &self.annotate {
             write!(f, "{:?}:{}", self.expr, annotate.get_type())
         } else {
             write!(f, "{:?}", self.expr)
@@ -123,11 +351,19 @@ impl Debug for Expr {
     }
 }
 
+/// Code is equal when it has the same values, locations are ignored
+impl PartialEq for Expr {
+    fn eq(&self, other: &Expr) -> bool {
+        self.expr == other.expr
+    }
+}
+
 impl Default for Expr {
     fn default() -> Self {
         Expr {
             expr: Type::Null,
             annotate: None,
+            span: None,
         }
     }
 }
@@ -1,134 +1,219 @@
-use std::ops::{Add, Div, Mul, Sub};
-
-#[derive(Debug, Clone, Copy)]
-pub struct Fraction {
-    numerator: isize,
-    denominator: isize,
+use num_bigint::BigInt;
+use num_integer::Integer;
+use num_rational::BigRational;
+use num_traits::{FromPrimitive, ToPrimitive, Zero};
+use std::cmp::Ordering;
+use std::ops::{Add, Div, Mul, Rem, Sub};
+
+/// Bits of the numerator or the denominator that exact power can make
+pub const MAX_POWER_BITS: u64 = 1 << 20;
+
+/// Exact rational number, it's promoted to big integers instead of overflowing
+#[derive(Debug, Clone)]
+pub struct Fraction(Repr);
+
+#[derive(Debug, Clone)]
+enum Repr {
+    // Always simplified and the denominator is positive
+    Small(i64, i64),
+    Big(Box<BigRational>),
 }
 
 impl Fraction {
-    pub fn new(number: f64) -> Self {
-        fn convert(number: f64) -> (isize, isize) {
-            const MAX_DENOMINATOR: isize = 1_000_000;
-
-            if number == 0.0 {
-                return (0, 1);
-            }
+    /// Fraction of the float that is known to be finite, like the constants in the code
+    pub(crate) fn new(number: f64) -> Self {
+        Fraction::try_new(number).expect("NaN and infinity have no fraction")
+    }
 
-            let mut best_numerator = 0;
-            let mut best_denominator = 1;
-            let mut best_diff = f64::MAX;
+    /// Convert the float into the simplest fraction that rounds to the same float, returns None for NaN and infinity
+    pub fn try_new(number: f64) -> Option<Self> {
+        if !number.is_finite() {
+            return None;
+        }
 
-            for denominator in 1..=MAX_DENOMINATOR {
-                let numerator = (number * denominator as f64).round() as isize;
-                let fraction = numerator as f64 / denominator as f64;
-                let diff = (number - fraction).abs();
+        // Whole numbers are converted directly
+        if number.fract() == 0.0 {
+            return Some(if number.abs() < i64::MAX as f64 {
+                Fraction(Repr::Small(number as i64, 1))
+            } else {
+                Fraction::big(BigRational::from_integer(
+                    BigInt::from_f64(number).unwrap_or_default(),
+                ))
+            });
+        }
 
-                if diff < best_diff {
-                    best_diff = diff;
-                    best_numerator = numerator;
-                    best_denominator = denominator;
+        // Convergents of the continued fraction approach the float from both sides
+        let (mut numerator, mut previous_numerator) = (1i128, 0i128);
+        let (mut denominator, mut previous_denominator) = (0i128, 1i128);
+        let mut rest = number;
+        while rest.is_finite() && rest.abs() < i64::MAX as f64 {
+            let term = rest.floor();
+            let next = (term as i128)
+                .checked_mul(numerator)
+                .and_then(|i| i.checked_add(previous_numerator))
+                .zip(
+                    (term as i128)
+                        .checked_mul(denominator)
+                        .and_then(|i| i.checked_add(previous_denominator)),
+                );
+            let Some((next_numerator, next_denominator)) = next else {
+                break;
+            };
+            (previous_numerator, numerator) = (numerator, next_numerator);
+            (previous_denominator, denominator) = (denominator, next_denominator);
 
-                    if diff == 0.0 {
-                        break;
-                    }
-                }
+            if numerator as f64 / denominator as f64 == number {
+                return Some(Fraction::small(numerator, denominator));
             }
-
-            (best_numerator, best_denominator)
+            rest = 1.0 / (rest - term);
         }
 
-        let result = convert(number);
-        let mut frac = Fraction {
-            numerator: result.0,
-            denominator: result.1,
-        };
+        // The exact binary value is used when the convergents don't fit
+        Fraction::from_float(number)
+    }
 
-        frac.simplify();
-        frac
+    /// Exact binary value of the float, returns None for NaN and infinity
+    pub fn from_float(number: f64) -> Option<Fraction> {
+        BigRational::from_float(number).map(Fraction::big)
     }
 
+    /// Parse integer like `42`, decimal like `0.1` or `1e-3` and fraction like `1/3` exactly
     pub fn from(value: String) -> Option<Fraction> {
-        let (numerator, denominator) = value.split_once("/")?;
-
-        let mut fraction = Fraction {
-            numerator: if let Ok(i) = numerator.trim().parse() {
-                i
-            } else {
-                return None;
-            },
-            denominator: if let Ok(i) = denominator.trim().parse() {
-                i
-            } else {
-                return None;
-            },
-        };
-        fraction.simplify();
-        Some(fraction)
+        let (numerator, denominator) = value.split_once("/").unwrap_or((&value, "1"));
+        let numerator = decimal(numerator.trim())?;
+        let denominator = decimal(denominator.trim())?;
+        if denominator.is_zero() {
+            return None;
+        }
+        Some(Fraction::big(numerator / denominator))
     }
 
     pub fn display(&self) -> String {
-        let mut selfs = self.clone();
-        selfs.simplify();
+        match &self.0 {
+            Repr::Small(numerator, 1) => numerator.to_string(),
+            Repr::Small(numerator, denominator) => format!("{numerator}/{denominator}"),
+            Repr::Big(ratio) => ratio.to_string(),
+        }
+    }
 
-        if selfs.denominator == 1 {
-            selfs.numerator.to_string()
-        } else {
-            format!("{}/{}", selfs.numerator, selfs.denominator)
+    pub fn is_zero(&self) -> bool {
+        match &self.0 {
+            Repr::Small(numerator, _) => *numerator == 0,
+            Repr::Big(ratio) => ratio.is_zero(),
         }
     }
 
-    // Function to simplify the fraction
-    fn simplify(&mut self) {
-        // Function to find the greatest common divisor (GCD) using Euclid's algorithm
-        fn gcd(mut a: isize, mut b: isize) -> isize {
-            while b != 0 {
-                let temp = b;
-                b = a % b;
-                a = temp;
-            }
-            a
+    /// Return the integer if the fraction is whole number that fits in `i32`
+    pub fn to_i32(&self) -> Option<i32> {
+        match &self.0 {
+            Repr::Small(numerator, 1) => i32::try_from(*numerator).ok(),
+            _ => None,
         }
+    }
 
-        let gcd = gcd(self.numerator.abs(), self.denominator.abs());
-        self.numerator /= gcd;
-        self.denominator /= gcd;
+    /// Raise to the integer power, fails when zero is raised to negative power
+    /// or the result would have more than `MAX_POWER_BITS` bits
+    pub fn pow(&self, exponent: i32) -> Result<Fraction, &'static str> {
+        if exponent < 0 && self.is_zero() {
+            return Err("division by zero");
+        }
+        let base = self.to_big();
+        // 0, 1 and -1 stay small, otherwise the bits grow with the exponent
+        let bits = base.numer().bits().max(base.denom().bits()) - 1;
+        if bits.saturating_mul(exponent.unsigned_abs() as u64) > MAX_POWER_BITS {
+            return Err("result of power is too large");
+        }
+        Ok(Fraction::big(num_traits::Pow::pow(base, exponent)))
     }
 
     // Function to convert the fraction to a floating-point number
     pub fn to_f64(&self) -> f64 {
-        self.numerator as f64 / self.denominator as f64
+        match &self.0 {
+            Repr::Small(numerator, denominator) => *numerator as f64 / *denominator as f64,
+            Repr::Big(ratio) => ratio.to_f64().unwrap_or(f64::NAN),
+        }
+    }
+
+    // Simplify the fraction made of machine integers, it's promoted if it doesn't fit
+    fn small(numerator: i128, denominator: i128) -> Fraction {
+        let gcd = numerator.gcd(&denominator);
+        let (mut numerator, mut denominator) = (numerator / gcd, denominator / gcd);
+        if denominator < 0 {
+            (numerator, denominator) = (-numerator, -denominator);
+        }
+        match (i64::try_from(numerator), i64::try_from(denominator)) {
+            (Ok(numerator), Ok(denominator)) => Fraction(Repr::Small(numerator, denominator)),
+            _ => Fraction(Repr::Big(Box::new(BigRational::new(
+                BigInt::from(numerator),
+                BigInt::from(denominator),
+            )))),
+        }
+    }
+
+    // Demote the big fraction when it fits in machine integers again
+    fn big(ratio: BigRational) -> Fraction {
+        match (ratio.numer().to_i64(), ratio.denom().to_i64()) {
+            (Some(numerator), Some(denominator)) => Fraction(Repr::Small(numerator, denominator)),
+            _ => Fraction(Repr::Big(Box::new(ratio))),
+        }
+    }
+
+    fn to_big(&self) -> BigRational {
+        match &self.0 {
+            Repr::Small(numerator, denominator) => {
+                BigRational::new_raw(BigInt::from(*numerator), BigInt::from(*denominator))
+            }
+            Repr::Big(ratio) => *ratio.clone(),
+        }
     }
 }
 
+// Read decimal notation from its digits, so that it's not rounded as binary float
+fn decimal(text: &str) -> Option<BigRational> {
+    // Larger exponent is left to float to avoid enormous numbers
+    const MAX_EXPONENT: u32 = 1000;
+
+    let (mantissa, exponent) = match text.split_once(['e', 'E']) {
+        Some((mantissa, exponent)) => (mantissa, exponent.parse::<i32>().ok()?),
+        None => (text, 0),
+    };
+    let (negative, mantissa) = match mantissa.strip_prefix('-') {
+        Some(mantissa) => (true, mantissa),
+        None => (false, mantissa.strip_prefix('+').unwrap_or(mantissa)),
+    };
+    let (integer, fraction) = mantissa.split_once('.').unwrap_or((mantissa, ""));
+    if integer.is_empty() && fraction.is_empty()
+        || !integer
+            .chars()
+            .chain(fraction.chars())
+            .all(|c| c.is_ascii_digit())
+        || exponent.unsigned_abs() > MAX_EXPONENT
+    {
+        return None;
+    }
+
+    let digits: BigInt = format!("{integer}{fraction}").parse().ok()?;
+    let ten = BigInt::from(10);
+    let mut result = BigRational::new(digits, ten.pow(fraction.len() as u32));
+    if exponent >= 0 {
+        result *= BigRational::from_integer(ten.pow(exponent as u32));
+    } else {
+        result /= BigRational::from_integer(ten.pow(exponent.unsigned_abs()));
+    }
+    Some(if negative { -result } else { result })
+}
+
 impl Add for Fraction {
     type Output = Fraction;
 
     fn add(self, other: Fraction) -> Fraction {
-        let number = other;
-        fn lcm(a: isize, b: isize) -> isize {
-            let gcd = |mut a: isize, mut b: isize| {
-                while b != 0 {
-                    let temp = b;
-                    b = a % b;
-                    a = temp;
-                }
-                a
-            };
-
-            (a * b) / gcd(a, b)
+        match (&self.0, &other.0) {
+            (Repr::Small(a, b), Repr::Small(c, d)) => {
+                let (a, b, c, d) = (*a as i128, *b as i128, *c as i128, *d as i128);
+                Fraction::small(a * d + c * b, b * d)
+            }
+            _ => Fraction::big(self.to_big() + other.to_big()),
         }
-
-        let denominator = lcm(self.denominator, number.denominator);
-        let numerator1 = self.numerator * (denominator / self.denominator);
-        let numerator2 = other.numerator * (denominator / other.denominator);
-
-        let mut result = Fraction {
-            numerator: numerator1 + numerator2,
-            denominator,
-        };
-        result.simplify();
-        result
     }
 }
 
@@ -136,30 +221,13 @@ impl Sub for Fraction {
     type Output = Fraction;
 
     fn sub(self, other: Fraction) -> Fraction {
-        let number = other;
-        fn lcm(a: isize, b: isize) -> isize {
-            let gcd = |mut a: isize, mut b: isize| {
-                while b != 0 {
-                    let temp = b;
-                    b = a % b;
-                    a = temp;
-                }
-                a
-            };
-
-            (a * b) / gcd(a, b)
+        match (&self.0, &other.0) {
+            (Repr::Small(a, b), Repr::Small(c, d)) => {
+                let (a, b, c, d) = (*a as i128, *b as i128, *c as i128, *d as i128);
+                Fraction::small(a * d - c * b, b * d)
+            }
+            _ => Fraction::big(self.to_big() - other.to_big()),
         }
-
-        let denominator = lcm(self.denominator, number.denominator);
-        let numerator1 = self.numerator * (denominator / self.denominator);
-        let numerator2 = other.numerator * (denominator / other.denominator);
-
-        let mut result = Fraction {
-            numerator: numerator1 - numerator2,
-            denominator,
-        };
-        result.simplify();
-        result
     }
 }
 
@@ -167,35 +235,75 @@ impl Mul for Fraction {
     type Output = Fraction;
 
     fn mul(self, other: Fraction) -> Fraction {
-        let number = other;
-        let mut result = Fraction {
-            numerator: self.numerator * number.numerator,
-            denominator: self.denominator * number.denominator,
-        };
-        result.simplify();
-        result
+        match (&self.0, &other.0) {
+            (Repr::Small(a, b), Repr::Small(c, d)) => {
+                Fraction::small(*a as i128 * *c as i128, *b as i128 * *d as i128)
+            }
+            _ => Fraction::big(self.to_big() * other.to_big()),
+        }
     }
 }
 
 impl Div for Fraction {
     type Output = Fraction;
 
+    /// Panics if the divisor is zero like integer division
     fn div(self, other: Fraction) -> Fraction {
-        let mut number = other;
-        (number.denominator, number.numerator) = (number.numerator, number.denominator);
+        if other.is_zero() {
+            panic!("attempt to divide fraction by zero");
+        }
+        match (&self.0, &other.0) {
+            (Repr::Small(a, b), Repr::Small(c, d)) => {
+                Fraction::small(*a as i128 * *d as i128, *b as i128 * *c as i128)
+            }
+            _ => Fraction::big(self.to_big() / other.to_big()),
+        }
+    }
+}
 
-        let mut result = self * number;
-        result.simplify();
-        result
+impl Rem for Fraction {
+    type Output = Fraction;
+
+    /// Remainder of the truncated division, its sign follows the dividend
+    fn rem(self, other: Fraction) -> Fraction {
+        if other.is_zero() {
+            panic!("attempt to calculate the remainder of fraction with a divisor of zero");
+        }
+        match (&self.0, &other.0) {
+            (Repr::Small(a, b), Repr::Small(c, d)) => {
+                let (a, b, c, d) = (*a as i128, *b as i128, *c as i128, *d as i128);
+                Fraction::small((a * d) % (c * b), b * d)
+            }
+            _ => Fraction::big(self.to_big() % other.to_big()),
+        }
     }
 }
 
 impl PartialEq for Fraction {
     fn eq(&self, other: &Fraction) -> bool {
-        let mut selfs = self.clone();
-        let mut other = other.clone();
-        selfs.simplify();
-        other.simplify();
-        self.denominator == other.denominator && self.numerator == other.numerator
+        match (&self.0, &other.0) {
+            (Repr::Small(a, b), Repr::Small(c, d)) => a == c && b == d,
+            _ => self.to_big() == other.to_big(),
+        }
+    }
+}
+
+impl Eq for Fraction {}
+
+impl PartialOrd for Fraction {
+    fn partial_cmp(&self, other: &Fraction) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Fraction {
+    fn cmp(&self, other: &Fraction) -> Ordering {
+        match (&self.0, &other.0) {
+            // Denominators are positive, so cross multiplication keeps the order
+            (Repr::Small(a, b), Repr::Small(c, d)) => {
+                (*a as i128 * *d as i128).cmp(&(*c as i128 * *b as i128))
+            }
+            _ => self.to_big().cmp(&other.to_big()),
+        }
     }
 }
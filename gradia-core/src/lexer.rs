@@ -0,0 +1,381 @@
+use crate::expr::{GradiaError, Span};
+
+/// Kind of the lexeme with its content
+#[derive(Clone, Debug, PartialEq)]
+pub enum Kind {
+    /// `(` or `{`
+    Open(char),
+    /// `)` or `}`
+    Close(char),
+    /// `'` that quotes the next symbol or list
+    Quote,
+    /// `` ` `` that quotes the next form except the unquoted parts
+    Quasiquote,
+    /// `,` that evaluates the next form inside of quasiquote
+    Unquote,
+    /// `,@` that splices the elements of the next form inside of quasiquote
+    UnquoteSplicing,
+    /// `#;` that comments out the next form
+    DatumComment,
+    /// String literal whose escape sequences are already replaced
+    String(String),
+    /// Number, bool, null or symbol
+    Atom(String),
+    /// Type annotation right after the value like `:number`
+    Annotation(String),
+}
+
+#[derive(Clone, Debug)]
+pub struct Lexeme {
+    pub kind: Kind,
+    pub span: Span,
+}
+
+/// Comment in the source with its syntax, kept for tools like formatter
+#[derive(Clone, Debug)]
+pub struct Trivia {
+    pub source: String,
+    pub span: Span,
+}
+
+/// Streaming lexer that reads each character of the source only once
+pub struct Lexer<'a> {
+    source: &'a str,
+    index: usize,
+    // Location of the source in the whole code
+    offset: usize,
+    // File that the source belongs to
+    file: usize,
+    line: usize,
+    column: usize,
+    // Whether the previous lexeme is a value that the colon annotates
+    attached: bool,
+    pub trivia: Vec<Trivia>,
+    // Syntax errors are collected and the lexing goes on
+    pub errors: Vec<GradiaError>,
+}
+
+impl<'a> Lexer<'a> {
+    /// Lex the source that begins at the `origin` location of the whole code
+    pub fn new(source: &'a str, origin: Span) -> Self {
+        Lexer {
+            source,
+            index: 0,
+            offset: origin.start,
+            file: origin.file,
+            line: origin.line,
+            column: origin.column,
+            attached: false,
+            trivia: Vec::new(),
+            errors: Vec::new(),
+        }
+    }
+
+    /// Source code of the span
+    pub fn slice(&self, span: Span) -> &'a str {
+        &self.source[span.start - self.offset..span.end - self.offset]
+    }
+
+    /// End of the source in the whole code
+    pub fn source_end(&self) -> usize {
+        self.offset + self.source.len()
+    }
+
+    /// Read the next lexeme, returns None at the end of the source
+    pub fn lex(&mut self) -> Option<Lexeme> {
+        if self.attached && self.peek() == Some(':') {
+            self.attached = false;
+            return Some(self.annotation());
+        }
+        self.attached = false;
+        self.skip_trivia();
+
+        let start = self.here();
+        let c = self.peek()?;
+        let kind = match c {
+            '(' | '{' => {
+                self.bump();
+                Kind::Open(c)
+            }
+            ')' | '}' => {
+                self.bump();
+                self.attached = true;
+                Kind::Close(c)
+            }
+            '\'' => {
+                self.bump();
+                Kind::Quote
+            }
+            '`' => {
+                self.bump();
+                Kind::Quasiquote
+            }
+            ',' => {
+                self.bump();
+                if self.peek() == Some('@') {
+                    self.bump();
+                    Kind::UnquoteSplicing
+                } else {
+                    Kind::Unquote
+                }
+            }
+            '#' if self.rest().starts_with("#;") => {
+                self.bump();
+                self.bump();
+                Kind::DatumComment
+            }
+            '"' => {
+                self.attached = true;
+                self.string(start)
+            }
+            'r' if raw_hashes(&self.rest()[1..]).is_some() => {
+                self.attached = true;
+                self.raw_string(start)
+            }
+            _ => {
+                self.attached = true;
+                self.atom()
+            }
+        };
+        Some(Lexeme {
+            kind,
+            span: self.until(start),
+        })
+    }
+
+    fn rest(&self) -> &'a str {
+        &self.source[self.index..]
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.rest().chars().next()
+    }
+
+    fn bump(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.index += c.len_utf8();
+        if c == '\n' {
+            self.line += 1;
+            self.column = 1;
+        } else {
+            self.column += 1;
+        }
+        Some(c)
+    }
+
+    /// Location of the current character
+    fn here(&self) -> Span {
+        Span {
+            start: self.offset + self.index,
+            end: self.offset + self.index,
+            line: self.line,
+            column: self.column,
+            file: self.file,
+        }
+    }
+
+    /// Span from the start to the current character
+    fn until(&self, start: Span) -> Span {
+        Span {
+            end: self.offset + self.index,
+            ..start
+        }
+    }
+
+    // Whitespaces and comments are skipped, the comments are kept as trivia
+    fn skip_trivia(&mut self) {
+        loop {
+            let start = self.here();
+            match self.peek() {
+                Some(c) if c.is_whitespace() => {
+                    self.bump();
+                }
+                Some(';') => {
+                    while self.peek().is_some_and(|c| c != '\n') {
+                        self.bump();
+                    }
+                    self.push_trivia(start);
+                }
+                Some('#') if self.rest().starts_with("#|") => {
+                    // Block comments can be nested like `#| #| |# |#`
+                    let mut depth = 0;
+                    loop {
+                        if self.rest().starts_with("#|") {
+                            depth += 1;
+                        } else if self.rest().starts_with("|#") {
+                            depth -= 1;
+                        } else if self.bump().is_some() {
+                            continue;
+                        } else {
+                            self.errors.push(GradiaError::Syntax(
+                                "there's not end of the block comment".to_string(),
+                                Some(start),
+                            ));
+                            break;
+                        }
+                        self.bump();
+                        self.bump();
+                        if depth == 0 {
+                            break;
+                        }
+                    }
+                    self.push_trivia(start);
+                }
+                _ => return,
+            }
+        }
+    }
+
+    fn push_trivia(&mut self, start: Span) {
+        let span = self.until(start);
+        self.trivia.push(Trivia {
+            source: self.slice(span).to_string(),
+            span,
+        });
+    }
+
+    // Symbol can begin with colon like `:key`, but colon after that starts the annotation
+    fn atom(&mut self) -> Kind {
+        let start = self.index;
+        self.bump();
+        while self
+            .peek()
+            .is_some_and(|c| !c.is_whitespace() && !"(){}\";:`,".contains(c))
+        {
+            self.bump();
+        }
+        Kind::Atom(self.source[start..self.index].to_string())
+    }
+
+    // Annotation continues until whitespace or the end of the enclosing parentheses
+    fn annotation(&mut self) -> Lexeme {
+        let start = self.here();
+        self.bump();
+        let mut depth = 0;
+        while let Some(c) = self.peek() {
+            match c {
+                '(' => depth += 1,
+                ')' | '}' if depth == 0 => break,
+                ')' => depth -= 1,
+                c if c.is_whitespace() && depth == 0 => break,
+                _ => {}
+            }
+            self.bump();
+        }
+        let span = self.until(start);
+        Lexeme {
+            kind: Kind::Annotation(self.slice(span)[1..].to_string()),
+            span,
+        }
+    }
+
+    fn string(&mut self, start: Span) -> Kind {
+        self.bump();
+        let begin = self.index;
+        let end = loop {
+            match self.bump() {
+                Some('"') => break self.index - 1,
+                // Escaped character never closes the string
+                Some('\\') if self.bump().is_some() => {}
+                Some(_) => {}
+                // The rest of the source is read as the string
+                None => {
+                    self.errors.push(GradiaError::Syntax(
+                        "there's not end of the quote".to_string(),
+                        Some(start),
+                    ));
+                    break self.index;
+                }
+            }
+        };
+        let content = &self.source[begin..end];
+        Kind::String(unescape(content).unwrap_or_else(|err| {
+            self.errors.push(err.at(Some(self.until(start))));
+            content.to_string()
+        }))
+    }
+
+    // Raw string like `r"..."` or `r#"..."#` has no escape sequences
+    fn raw_string(&mut self, start: Span) -> Kind {
+        let hashes = raw_hashes(&self.rest()[1..]).unwrap_or_default();
+        let begin = self.index + hashes + 2;
+        let end = format!("\"{}", "#".repeat(hashes));
+        let (length, last) = match self.source[begin..].find(&end) {
+            Some(length) => (length, begin + length + end.len()),
+            None => {
+                self.errors.push(GradiaError::Syntax(
+                    "there's not end of the quote".to_string(),
+                    Some(start),
+                ));
+                (self.source.len() - begin, self.source.len())
+            }
+        };
+        while self.index < last {
+            self.bump();
+        }
+        Kind::String(self.source[begin..begin + length].to_string())
+    }
+}
+
+impl Iterator for Lexer<'_> {
+    type Item = Lexeme;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.lex()
+    }
+}
+
+/// Number of `#` if the source after `r` opens raw string
+fn raw_hashes(source: &str) -> Option<usize> {
+    let hashes = source.len() - source.trim_start_matches('#').len();
+    source[hashes..].starts_with('"').then_some(hashes)
+}
+
+/// Replace escape sequences of string literal with the characters
+fn unescape(source: &str) -> Result<String, GradiaError> {
+    let mut result = String::new();
+    let mut chars = source.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            result.push(c);
+            continue;
+        }
+        result.push(match chars.next() {
+            Some('"') => '"',
+            Some('\\') => '\\',
+            Some('n') => '\n',
+            Some('t') => '\t',
+            Some('r') => '\r',
+            Some('0') => '\0',
+            // Unicode code point in hex like `\u{3042}`
+            Some('u') => {
+                let rest = chars.as_str();
+                let Some((code, rest)) = rest.strip_prefix('{').and_then(|i| i.split_once('}'))
+                else {
+                    return Err(GradiaError::Syntax(
+                        "unicode escape should be like `\\u{3042}`".to_string(),
+                        None,
+                    ));
+                };
+                let Some(c) = u32::from_str_radix(code, 16).ok().and_then(char::from_u32) else {
+                    return Err(GradiaError::Syntax(
+                        format!("`{code}` is not valid unicode code point"),
+                        None,
+                    ));
+                };
+                chars = rest.chars();
+                c
+            }
+            other => {
+                return Err(GradiaError::Syntax(
+                    format!(
+                        "unknown escape sequence `\\{}`",
+                        other.map(String::from).unwrap_or_default()
+                    ),
+                    None,
+                ))
+            }
+        });
+    }
+    Ok(result)
+}
@@ -1,5 +1,10 @@
 pub mod expr;
 pub mod fraction;
+pub mod lexer;
+pub mod macros;
+pub mod module;
 pub mod parser;
+pub mod pattern;
 pub mod std;
+pub mod typecheck;
 pub mod types;
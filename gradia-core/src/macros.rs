@@ -0,0 +1,280 @@
+use crate::expr::{Expr, GradiaError, Span};
+use crate::module;
+use crate::pattern::variables;
+use crate::types::{Function, Lambda, Scope, Type};
+use std::cell::Cell;
+use std::collections::HashSet;
+
+thread_local! {
+    // Counter of the symbols made by `gensym`
+    static GENSYM: Cell<usize> = const { Cell::new(0) };
+}
+
+/// Expand the macro calls in the code before it's evaluated, `defmacro` at the top level defines the macro here
+pub fn expand(expr: &Expr, scope: &mut Scope) -> Result<Expr, GradiaError> {
+    expand_form(expr, scope, true, &HashSet::new())
+}
+
+// Macro defined inside of function is left to the runtime, as its scope exists only there.
+// Variables in `bound` are bound by the code around, so that they hide the macros of the same name
+fn expand_form(
+    expr: &Expr,
+    scope: &mut Scope,
+    top: bool,
+    bound: &HashSet<String>,
+) -> Result<Expr, GradiaError> {
+    let Type::Expr(list) = &expr.expr else {
+        return Ok(expr.clone());
+    };
+    let head = match list.first().map(|i| &i.expr) {
+        Some(Type::Symbol(name)) if !bound.contains(name) => scope.get(name).map(|i| (name, i)),
+        _ => None,
+    };
+    let expanded = match head {
+        Some((_, Type::Function(Function::Macro(lambda)))) => {
+            let mut code = call(&lambda, &list[1..], expr.span)?;
+            code.annotate = expr.annotate.clone();
+            return expand_form(&code, scope, top, bound);
+        }
+        Some((name, Type::Function(Function::Special(_)))) => match name.as_str() {
+            // Macro is available for the following code
+            "defmacro" => {
+                if top {
+                    expr.eval(scope)?;
+                }
+                return Ok(expr.clone());
+            }
+            // Only the macros of the module are loaded, and the module itself runs when the import is evaluated
+            "import" => {
+                if top {
+                    import_macros(&list[1..], scope);
+                }
+                return Ok(expr.clone());
+            }
+            // Template is the data, so that only the unquoted code is evaluated
+            "quasiquote" | "defrecord" => return Ok(expr.clone()),
+            "let" | "let*" => expand_let(list, scope, bound)?,
+            "lambda" => match list.get(1) {
+                Some(params) => {
+                    let inner = shadow(bound, variables(params));
+                    [&list[..2], &expand_all(&list[2..], scope, &inner)?].concat()
+                }
+                None => list.clone(),
+            },
+            "define" => match list.get(1).map(|i| &i.expr) {
+                // Function is `(define (name params...) body...)`, its name and parameters are bound in the body
+                Some(Type::Expr(header) | Type::List(header)) => {
+                    let inner = shadow(bound, header.iter().flat_map(variables));
+                    [&list[..2], &expand_all(&list[2..], scope, &inner)?].concat()
+                }
+                _ => [
+                    &list[..list.len().min(2)],
+                    &expand_all(&list[2..], scope, bound)?,
+                ]
+                .concat(),
+            },
+            "define-values" => [
+                &list[..list.len().min(2)],
+                &expand_all(&list[2..], scope, bound)?,
+            ]
+            .concat(),
+            "match" => expand_match(list, scope, bound)?,
+            _ => expand_all(list, scope, bound)?,
+        },
+        _ => expand_all(list, scope, bound)?,
+    };
+    Ok(Expr {
+        expr: Type::Expr(expanded),
+        annotate: expr.annotate.clone(),
+        span: expr.span,
+    })
+}
+
+// Bind the macros exported by the module of `(import "path" options...)` whose path is literal
+fn import_macros(params: &[Expr], scope: &Scope) {
+    let Some((
+        Expr {
+            expr: Type::String(path),
+            ..
+        },
+        options,
+    )) = params.split_first()
+    else {
+        return;
+    };
+    let Ok(options) = module::Options::parse(options) else {
+        return;
+    };
+    for (name, value) in module::macros(path, scope).iter() {
+        if let Some(name) = options.name(name) {
+            scope.insert(name, value.clone());
+        }
+    }
+}
+
+fn expand_all(
+    list: &[Expr],
+    scope: &mut Scope,
+    bound: &HashSet<String>,
+) -> Result<Vec<Expr>, GradiaError> {
+    list.iter()
+        .map(|i| expand_form(i, scope, false, bound))
+        .collect()
+}
+
+// Values of `(let ((pattern value)...) body...)` are expanded, and the patterns are kept as they are
+fn expand_let(
+    list: &[Expr],
+    scope: &mut Scope,
+    bound: &HashSet<String>,
+) -> Result<Vec<Expr>, GradiaError> {
+    let Some(Type::Expr(bindings) | Type::List(bindings)) = list.get(1).map(|i| &i.expr) else {
+        return expand_all(list, scope, bound);
+    };
+    // Variable of `let*` is visible from the values after it
+    let sequential = matches!(&list[0].expr, Type::Symbol(name) if name == "let*");
+    let mut inner = bound.clone();
+    let mut expanded = Vec::with_capacity(bindings.len());
+    for binding in bindings {
+        let item = match &binding.expr {
+            Type::Expr(pair) | Type::List(pair) if pair.len() == 2 => {
+                let value = expand_form(
+                    &pair[1],
+                    scope,
+                    false,
+                    if sequential { &inner } else { bound },
+                )?;
+                inner.extend(variables(&pair[0]).iter().map(|i| i.expr.get_string()));
+                Expr {
+                    expr: Type::Expr(vec![pair[0].clone(), value]),
+                    ..binding.clone()
+                }
+            }
+            _ => binding.clone(),
+        };
+        expanded.push(item);
+    }
+    let bindings = Expr {
+        expr: Type::Expr(expanded),
+        ..list[1].clone()
+    };
+    Ok([
+        vec![list[0].clone(), bindings],
+        expand_all(&list[2..], scope, &inner)?,
+    ]
+    .concat())
+}
+
+// Patterns of `(match value (pattern code...)...)` are kept, and their variables are bound in the code
+fn expand_match(
+    list: &[Expr],
+    scope: &mut Scope,
+    bound: &HashSet<String>,
+) -> Result<Vec<Expr>, GradiaError> {
+    let mut expanded = expand_all(&list[..list.len().min(2)], scope, bound)?;
+    for clause in list.iter().skip(2) {
+        let Type::Expr(items) = &clause.expr else {
+            expanded.push(clause.clone());
+            continue;
+        };
+        let Some((pattern, code)) = items.split_first() else {
+            expanded.push(clause.clone());
+            continue;
+        };
+        let inner = shadow(bound, variables(pattern));
+        expanded.push(Expr {
+            expr: Type::Expr([vec![pattern.clone()], expand_all(code, scope, &inner)?].concat()),
+            ..clause.clone()
+        });
+    }
+    Ok(expanded)
+}
+
+// Names bound outside and the new variables
+fn shadow<'a>(
+    bound: &HashSet<String>,
+    variables: impl IntoIterator<Item = &'a Expr>,
+) -> HashSet<String> {
+    let mut inner = bound.clone();
+    inner.extend(variables.into_iter().map(|i| i.expr.get_string()));
+    inner
+}
+
+/// Run the macro with the code of arguments as data, and return the code that it made
+pub fn call(macro_: &Lambda, args: &[Expr], span: Option<Span>) -> Result<Expr, GradiaError> {
+    let values = args.iter().map(|i| i.expr.clone()).collect();
+    let mut scope = macro_.bind(values, args).map_err(|err| err.at(span))?;
+
+    let mut result = Type::Null;
+    for line in &macro_.body {
+        result = line.eval(&mut scope)?;
+    }
+    if let Some(class) = &macro_.result {
+        result = class
+            .check(result, &macro_.scope)
+            .map_err(|err| err.at(span))?;
+    }
+    Ok(to_code(result, span))
+}
+
+/// Code of the data, list is read as the expression
+pub fn to_code(value: Type, span: Option<Span>) -> Expr {
+    Expr {
+        expr: match value {
+            Type::List(list) => Type::Expr(list),
+            other => other,
+        },
+        annotate: None,
+        span,
+    }
+}
+
+/// Data of the template whose unquoted forms are replaced with their values
+pub fn quasiquote(template: &Expr, scope: &mut Scope) -> Result<Expr, GradiaError> {
+    let (Type::Expr(list) | Type::List(list)) = &template.expr else {
+        return Ok(template.clone());
+    };
+    if let Some(value) = unquoted(list, "unquote") {
+        return Ok(Expr {
+            expr: value.eval(scope)?,
+            annotate: None,
+            span: template.span,
+        });
+    }
+
+    let mut result = Vec::with_capacity(list.len());
+    for item in list {
+        if let Type::Expr(inner) = &item.expr {
+            if let Some(form) = unquoted(inner, "unquote-splicing") {
+                result.extend(form.eval(scope)?.get_list());
+                continue;
+            }
+        }
+        result.push(quasiquote(item, scope)?);
+    }
+    Ok(Expr {
+        expr: match template.expr {
+            Type::List(_) => Type::List(result),
+            _ => Type::Expr(result),
+        },
+        annotate: template.annotate.clone(),
+        span: template.span,
+    })
+}
+
+/// Fresh symbol that differs from the other generated ones, for the variables made by macro
+pub fn gensym(prefix: &str) -> Type {
+    let count = GENSYM.with(|i| {
+        i.set(i.get() + 1);
+        i.get()
+    });
+    Type::Symbol(format!("{prefix}#{count}"))
+}
+
+/// Form inside of unquote like `(unquote form)`
+fn unquoted<'a>(list: &'a [Expr], name: &str) -> Option<&'a Expr> {
+    match list {
+        [head, form] if matches!(&head.expr, Type::Symbol(symbol) if symbol == name) => Some(form),
+        _ => None,
+    }
+}
@@ -0,0 +1,332 @@
+use crate::expr::{Expr, GradiaError, Span};
+use crate::macros;
+use crate::parser::{Parser, ORIGIN};
+use crate::types::{Function, RecordType, Scope, Type};
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fs::read_to_string;
+use std::path::{Component, Path, PathBuf};
+use std::rc::Rc;
+
+thread_local! {
+    // Path and source of the imported files, `file` of their spans is the index from 1
+    static SOURCES: RefCell<Vec<(String, String)>> = const { RefCell::new(Vec::new()) };
+}
+
+/// Where the source of modules comes from, so that hosts without file system can provide it
+pub trait Loader {
+    /// Identity of the module imported by the path from the file, it's the key of the loaded modules
+    fn resolve(&self, from: Option<&str>, path: &str) -> String {
+        resolve_path(from, path)
+    }
+
+    /// Source code of the resolved module
+    fn load(&self, path: &str) -> Result<String, String>;
+}
+
+/// Loader that reads the modules from the file system
+pub struct FileLoader;
+
+impl Loader for FileLoader {
+    fn load(&self, path: &str) -> Result<String, String> {
+        read_to_string(path).map_err(|err| err.to_string())
+    }
+}
+
+/// Modules of the program shared by all of its files
+pub struct Modules {
+    loader: Box<dyn Loader>,
+    // Built-in functions that each file is evaluated on
+    prelude: Scope,
+    loaded: RefCell<HashMap<String, Rc<Exports>>>,
+    // Files being imported now, from the main file
+    loading: RefCell<Vec<String>>,
+    // Macros exported by the files, that are loaded for the expansion before the files run
+    macros: RefCell<HashMap<String, Rc<Macros>>>,
+}
+
+/// Exported macros and their names
+pub type Macros = Vec<(String, Type)>;
+
+/// Exported values and record types of the module, in the order of `export`
+#[derive(Default)]
+pub struct Exports {
+    pub values: Vec<(String, Type)>,
+    pub records: Vec<(String, Rc<RecordType>)>,
+}
+
+/// Options of `import`, `:as prefix` for the namespace and `:only (names...)`
+pub struct Options {
+    prefix: String,
+    only: Option<Vec<String>>,
+}
+
+impl Options {
+    /// Read the options that follow the path of `import`
+    pub fn parse(options: &[Expr]) -> Result<Self, GradiaError> {
+        let mut prefix = String::new();
+        let mut only = None;
+        for pair in options.chunks(2) {
+            match (pair[0].expr.get_string().as_str(), pair.get(1)) {
+                (":as", Some(name)) => prefix = format!("{}/", name.expr.get_string()),
+                (":only", Some(names)) => {
+                    only = Some(
+                        names
+                            .expr
+                            .get_list()
+                            .iter()
+                            .map(|i| i.expr.get_string())
+                            .collect(),
+                    )
+                }
+                (option, _) => {
+                    return Err(GradiaError::Syntax(
+                        format!("import option `{option}` needs `:as name` or `:only (names...)`"),
+                        pair[0].span,
+                    ))
+                }
+            }
+        }
+        Ok(Options { prefix, only })
+    }
+
+    /// Name that the exported one is bound as, None if it isn't imported
+    pub fn name(&self, name: &str) -> Option<String> {
+        match &self.only {
+            Some(only) if !only.iter().any(|i| i == name) => None,
+            _ => Some(format!("{}{name}", self.prefix)),
+        }
+    }
+
+    /// Names of `:only` that the module doesn't export
+    pub fn missing<'a>(&'a self, exported: &[&str]) -> Vec<&'a str> {
+        self.only
+            .iter()
+            .flatten()
+            .filter(|i| !exported.contains(&i.as_str()))
+            .map(|i| i.as_str())
+            .collect()
+    }
+}
+
+/// File of the program and the names it exports
+pub struct Module {
+    pub path: Option<String>,
+    pub modules: Rc<Modules>,
+    pub exports: RefCell<Vec<String>>,
+}
+
+impl Module {
+    /// Identity of the module imported by the path from this file
+    pub fn resolve(&self, path: &str) -> String {
+        self.modules.loader.resolve(self.path.as_deref(), path)
+    }
+
+    /// Forms of the resolved module and the scope to expand them on, the module isn't run
+    pub fn read(&self, path: &str) -> Result<(Vec<Expr>, Scope), GradiaError> {
+        let source = self
+            .modules
+            .loader
+            .load(path)
+            .map_err(|err| GradiaError::Runtime(format!("can't load `{path}`: {err}"), None))?;
+        let scope = self.modules.prelude.with_module(Rc::new(Module {
+            path: Some(path.to_string()),
+            modules: self.modules.clone(),
+            exports: RefCell::new(Vec::new()),
+        }));
+        Ok((parse_file(path, &source)?, scope))
+    }
+}
+
+/// Scope of the main file, whose imports are loaded by the loader and evaluated on the prelude
+pub fn program(prelude: Scope, loader: impl Loader + 'static, path: Option<String>) -> Scope {
+    let modules = Rc::new(Modules {
+        loader: Box::new(loader),
+        prelude: prelude.clone(),
+        loaded: RefCell::new(HashMap::new()),
+        loading: RefCell::new(path.iter().cloned().collect()),
+        macros: RefCell::new(HashMap::new()),
+    });
+    prelude.with_module(Rc::new(Module {
+        path,
+        modules,
+        exports: RefCell::new(Vec::new()),
+    }))
+}
+
+/// Exports of the module imported from the scope, it's evaluated only when it's imported first
+pub fn import(path: &str, scope: &Scope) -> Result<Rc<Exports>, GradiaError> {
+    let Some(current) = scope.module() else {
+        return Err(GradiaError::Runtime(
+            "import isn't available as there's no module loader".to_string(),
+            None,
+        ));
+    };
+    let modules = &current.modules;
+    let path = current.resolve(path);
+    if let Some(exports) = modules.loaded.borrow().get(&path) {
+        return Ok(exports.clone());
+    }
+
+    let loading = modules.loading.borrow().clone();
+    if let Some(index) = loading.iter().position(|i| *i == path) {
+        let cycle = [&loading[index..], &[path]].concat();
+        return Err(GradiaError::Runtime(
+            format!("there's import cycle `{}`", cycle.join("` -> `")),
+            None,
+        ));
+    }
+
+    let source = modules
+        .loader
+        .load(&path)
+        .map_err(|err| GradiaError::Runtime(format!("can't load `{path}`: {err}"), None))?;
+    modules.loading.borrow_mut().push(path.clone());
+    let exports = evaluate(modules, &path, &source);
+    modules.loading.borrow_mut().pop();
+
+    let exports = Rc::new(exports?);
+    modules.loaded.borrow_mut().insert(path, exports.clone());
+    Ok(exports)
+}
+
+/// Macros exported by the module imported from the scope, only `defmacro` and `import` of the module are run.
+/// Module that can't be loaded has no macros here, and the error is left to the runtime
+pub fn macros(path: &str, scope: &Scope) -> Rc<Macros> {
+    let Some(current) = scope.module() else {
+        return Rc::default();
+    };
+    let modules = &current.modules;
+    let path = current.resolve(path);
+    if let Some(macros) = modules.macros.borrow().get(&path) {
+        return macros.clone();
+    }
+    // Module in the import cycle has no macros until it's loaded
+    modules
+        .macros
+        .borrow_mut()
+        .insert(path.clone(), Rc::default());
+
+    let Ok((forms, mut scope)) = current.read(&path) else {
+        return Rc::default();
+    };
+    for form in &forms {
+        if is_form(form, &["defmacro", "import"]) && macros::expand(form, &mut scope).is_err() {
+            return Rc::default();
+        }
+    }
+    let macros: Rc<Macros> = Rc::new(
+        exported(&forms)
+            .into_iter()
+            .filter_map(|name| match scope.get(&name) {
+                Some(value @ Type::Function(Function::Macro(_))) => Some((name, value)),
+                _ => None,
+            })
+            .collect(),
+    );
+    modules.macros.borrow_mut().insert(path, macros.clone());
+    macros
+}
+
+/// Names exported by the `export` forms at the top level of the module
+pub fn exported(forms: &[Expr]) -> Vec<String> {
+    forms
+        .iter()
+        .filter(|i| is_form(i, &["export"]))
+        .flat_map(|i| i.expr.get_list()[1..].to_vec())
+        .map(|i| i.expr.get_string())
+        .collect()
+}
+
+// Whether the form is the call of one of the names
+fn is_form(form: &Expr, names: &[&str]) -> bool {
+    match &form.expr {
+        Type::Expr(list) => {
+            matches!(list.first().map(|i| &i.expr), Some(Type::Symbol(name)) if names.contains(&name.as_str()))
+        }
+        _ => false,
+    }
+}
+
+/// Forms of the imported file, their locations refer to the file
+pub fn parse_file(path: &str, source: &str) -> Result<Vec<Expr>, GradiaError> {
+    let file = register(path, source);
+    let mut parser = Parser::new(source, Span { file, ..ORIGIN });
+    let mut forms = Vec::new();
+    while let Some(expr) = parser.form() {
+        forms.push(expr);
+    }
+    match parser.finish().1.into_iter().next() {
+        Some(err) => Err(err),
+        None => Ok(forms),
+    }
+}
+
+/// Path and source of the imported file that the spans refer to
+pub fn source(file: usize) -> Option<(String, String)> {
+    SOURCES.with(|i| i.borrow().get(file.checked_sub(1)?).cloned())
+}
+
+// Number of the file that is the same for the same source
+fn register(path: &str, source: &str) -> usize {
+    SOURCES.with(|i| {
+        let mut sources = i.borrow_mut();
+        match sources.iter().position(|(p, s)| p == path && s == source) {
+            Some(index) => index + 1,
+            None => {
+                sources.push((path.to_string(), source.to_string()));
+                sources.len()
+            }
+        }
+    })
+}
+
+/// Path of the file relative to the importing file, `..` and `.` are resolved
+pub fn resolve_path(from: Option<&str>, path: &str) -> String {
+    let base = from
+        .and_then(|i| Path::new(i).parent())
+        .unwrap_or(Path::new(""));
+    let mut result = PathBuf::new();
+    for component in base.join(path).components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir
+                if matches!(result.components().next_back(), Some(Component::Normal(_))) =>
+            {
+                result.pop();
+            }
+            other => result.push(other),
+        }
+    }
+    result.to_string_lossy().to_string()
+}
+
+// Evaluate the file on its own scope, and collect the exported values
+fn evaluate(modules: &Rc<Modules>, path: &str, source: &str) -> Result<Exports, GradiaError> {
+    let module = Rc::new(Module {
+        path: Some(path.to_string()),
+        modules: modules.clone(),
+        exports: RefCell::new(Vec::new()),
+    });
+    let mut scope = modules.prelude.with_module(module.clone());
+    // Error keeps its location, whose span refers to the module's own source
+    for expr in parse_file(path, source)? {
+        macros::expand(&expr, &mut scope)?.eval(&mut scope)?;
+    }
+
+    // Exported name is the variable or the record type, or both of them
+    let mut exports = Exports::default();
+    for name in module.exports.borrow().iter() {
+        let value = scope.get(name);
+        let record = scope.record(name);
+        if value.is_none() && record.is_none() {
+            return Err(GradiaError::Runtime(
+                format!("exported `{name}` isn't defined in `{path}`"),
+                None,
+            ));
+        }
+        exports.values.extend(value.map(|i| (name.clone(), i)));
+        exports.records.extend(record.map(|i| (name.clone(), i)));
+    }
+    Ok(exports)
+}
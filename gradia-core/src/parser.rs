@@ -1,195 +1,382 @@
-use crate::expr::{Expr, GradiaError};
+use crate::expr::{Expr, GradiaError, Span};
 use crate::fraction::Fraction;
+use crate::lexer::{Kind, Lexeme, Lexer};
 use crate::types::{Class, Type};
 
-pub fn parse(token: (String, Option<String>)) -> Result<Expr, GradiaError> {
-    // Setting type annotation
-    let annotate = if let Some(annotate) = token.1 {
-        Class::from(annotate)?
-    } else {
-        None
+pub use crate::lexer::Trivia;
+
+/// Source text of one form with its type annotation and location
+#[derive(Clone, Debug)]
+pub struct Token {
+    pub source: String,
+    pub annotate: Option<String>,
+    pub span: Span,
+}
+
+/// Source and annotation of the form that isn't located in any code
+impl From<(String, Option<String>)> for Token {
+    fn from((source, annotate): (String, Option<String>)) -> Self {
+        Token {
+            source,
+            annotate,
+            span: ORIGIN,
+        }
+    }
+}
+
+/// Location of the beginning of the whole code
+pub const ORIGIN: Span = Span {
+    start: 0,
+    end: 0,
+    line: 1,
+    column: 1,
+    file: 0,
+};
+
+/// Build the expression of one form, its source is read at the location of the token
+pub fn parse(token: impl Into<Token>) -> Result<Expr, GradiaError> {
+    let token = token.into();
+    let mut parser = Parser::new(&token.source, token.span);
+    let expr = parser.form();
+    if let Some(err) = parser.finish().1.into_iter().next() {
+        return Err(err);
+    }
+    let Some(mut expr) = expr else {
+        return Err(GradiaError::Syntax(
+            "there's no code to parse".to_string(),
+            Some(token.span),
+        ));
     };
+    if let Some(annotate) = token.annotate {
+        expr.annotate = Class::from(annotate).map_err(|err| err.at(expr.span))?;
+    }
+    Ok(expr)
+}
 
-    let mut token = token.0.trim().to_string();
-    Ok(
-        // Number case
-        if let Ok(n) = token.parse::<f64>() {
-            Expr {
-                expr: Type::Number(Fraction::new(n)),
-                annotate,
-            }
-        // Fraction case
-        } else if let Some(n) = Fraction::from(token.clone()) {
-            Expr {
-                expr: Type::Number(n),
-                annotate,
-            }
-        // Bool calse
-        } else if let Ok(b) = token.parse::<bool>() {
-            Expr {
-                expr: Type::Bool(b),
-                annotate,
-            }
-        // Null calse
-        } else if token == "null".to_string() {
-            Expr {
-                expr: Type::Null,
-                annotate,
-            }
-        // String calse
-        } else if token.starts_with('"') && token.ends_with('"') {
-            token.remove(0); // Removing outer syntax
-            token.remove(token.len() - 1);
-            Expr {
-                expr: Type::String(token),
-                annotate,
-            }
-        // Expression case
-        } else if token.starts_with('(') && token.ends_with(')') {
-            token.remove(0); // Removing outer syntax
-            token.remove(token.len() - 1);
-            Expr {
-                expr: {
-                    let mut list = vec![];
-                    for i in tokenize(token)? {
-                        list.push(parse(i)?)
-                    }
-                    Type::Expr(list)
-                },
-                annotate,
-            }
-        // List case
-        } else if token.starts_with("'(") && token.ends_with(')') {
-            token.remove(0); // Removing outer syntax
-            token.remove(0);
-            token.remove(token.len() - 1);
-            Expr {
-                expr: {
-                    let mut list = vec![];
-                    for i in tokenize(token)? {
-                        list.push(parse(i)?)
-                    }
-                    Type::List(list)
-                },
-                annotate,
-            }
-        // Symbol that explicitly
-        } else if token.starts_with("'") {
-            token.remove(0); // Removing outer syntax
-            Expr {
-                expr: Type::Symbol(token),
-                annotate,
-            }
-        // Other case will be symbol
-        } else {
-            Expr {
-                expr: Type::Symbol(token.clone()),
-                annotate,
-            }
-        },
-    )
+pub fn tokenize(input: String) -> Result<Vec<Token>, GradiaError> {
+    Ok(tokenize_with_trivia(input)?.0)
 }
 
-pub fn tokenize(input: String) -> Result<Vec<(String, Option<String>)>, GradiaError> {
-    let mut tokens: Vec<(String, Option<String>)> = Vec::new();
-    let mut current_token = String::new();
-    let mut after_colon = String::new();
-    let mut is_colon = false;
-    let mut in_parentheses: usize = 0;
-    let mut in_quote = false;
-
-    for c in input.chars() {
-        match c {
-            '(' if !in_quote => {
-                if is_colon {
-                    after_colon.push(c);
-                } else {
-                    current_token.push(c);
-                }
-                in_parentheses += 1;
+/// Tokenize the source and also return the comments in it
+pub fn tokenize_with_trivia(input: String) -> Result<(Vec<Token>, Vec<Trivia>), GradiaError> {
+    let mut parser = Parser::new(&input, ORIGIN);
+    let mut tokens = Vec::new();
+    while let Some(token) = parser.token() {
+        tokens.push(token);
+    }
+    let (trivia, errors) = parser.finish();
+    match errors.into_iter().next() {
+        Some(err) => Err(err),
+        None => Ok((tokens, trivia)),
+    }
+}
+
+/// Parse all forms of the code
+pub fn read(input: &str) -> Result<Vec<Expr>, GradiaError> {
+    let (program, errors) = read_recovering(input);
+    match errors.into_iter().next() {
+        Some(err) => Err(err),
+        None => Ok(program),
+    }
+}
+
+/// Parse all forms without stopping at syntax errors, and return the partial tree with all of them
+pub fn read_recovering(input: &str) -> (Vec<Expr>, Vec<GradiaError>) {
+    let mut parser = Parser::new(input, ORIGIN);
+    let mut program = Vec::new();
+    while let Some(expr) = parser.form() {
+        program.push(expr);
+    }
+    (program, parser.finish().1)
+}
+
+/// Recursive-descent parser that builds expressions from the lexemes in one pass.
+/// Syntax errors are collected and the parsing goes on, so that the rest of the code is still read
+pub struct Parser<'a> {
+    lexer: Lexer<'a>,
+    peeked: Option<Lexeme>,
+    // Number of the parentheses that aren't closed yet
+    depth: usize,
+}
+
+impl<'a> Parser<'a> {
+    /// Parse the source that begins at the `origin` location of the whole code
+    pub fn new(source: &'a str, origin: Span) -> Self {
+        Parser {
+            lexer: Lexer::new(source, origin),
+            peeked: None,
+            depth: 0,
+        }
+    }
+
+    /// Parse the next form, returns None at the end of the source or the enclosing list
+    pub fn form(&mut self) -> Option<Expr> {
+        let (expr, annotation) = self.annotated()?;
+        Some(self.annotate(expr, annotation))
+    }
+
+    /// Finish parsing and return the comments and the syntax errors in the source
+    pub fn finish(self) -> (Vec<Trivia>, Vec<GradiaError>) {
+        let mut trivia = self.lexer.trivia;
+        trivia.sort_by_key(|i| i.span.start);
+        (trivia, self.lexer.errors)
+    }
+
+    // Read the next form keeping its source text for `Token`
+    fn token(&mut self) -> Option<Token> {
+        let (expr, annotation) = self.annotated()?;
+        let source = self.lexer.slice(expr.span.unwrap_or_default()).to_string();
+        let annotate_source = match &annotation {
+            Some(Lexeme {
+                kind: Kind::Annotation(annotate),
+                ..
+            }) => Some(annotate.clone()),
+            _ => None,
+        };
+        Some(Token {
+            source,
+            annotate: annotate_source,
+            span: expr.span.unwrap_or_default(),
+        })
+    }
+
+    fn peek(&mut self) -> Option<&Lexeme> {
+        if self.peeked.is_none() {
+            self.peeked = self.lexer.lex();
+        }
+        self.peeked.as_ref()
+    }
+
+    fn next(&mut self) -> Option<Lexeme> {
+        self.peeked.take().or_else(|| self.lexer.lex())
+    }
+
+    fn error(&mut self, message: &str, span: Span) {
+        self.lexer
+            .errors
+            .push(GradiaError::Syntax(message.to_string(), Some(span)));
+    }
+
+    // Parse the next form and the annotation after it that isn't applied yet
+    fn annotated(&mut self) -> Option<(Expr, Option<Lexeme>)> {
+        let lexeme = loop {
+            let close = match self.peek()? {
+                Lexeme {
+                    kind: Kind::Close(_),
+                    span,
+                } => *span,
+                _ => break self.next()?,
+            };
+            // End of the list is left for the caller
+            if self.depth > 0 {
+                return None;
             }
-            ')' if !in_quote => {
-                if is_colon {
-                    after_colon.push(c);
-                } else {
-                    current_token.push(c);
+            self.error("there's duplicate end of the parentheses", close);
+            self.next();
+        };
+
+        let span = lexeme.span;
+        let expr = match lexeme.kind {
+            Kind::Atom(atom) => Expr {
+                expr: literal(atom),
+                annotate: None,
+                span: Some(span),
+            },
+            Kind::String(string) => Expr {
+                expr: Type::String(string),
+                annotate: None,
+                span: Some(span),
+            },
+            // Expression case
+            Kind::Open('(') => {
+                let (list, span) = self.list(span, ')');
+                Expr {
+                    expr: Type::Expr(list),
+                    annotate: None,
+                    span: Some(span),
                 }
-                if in_parentheses > 0 {
-                    in_parentheses -= 1;
-                } else {
-                    return Err(GradiaError::Syntax(
-                        "there's duplicate end of the parentheses".to_string(),
-                    ));
+            }
+            // Map case, it's made from the keys and values by `hash-map`
+            Kind::Open(_) => {
+                let mut list = vec![Expr {
+                    expr: Type::Symbol("hash-map".to_string()),
+                    annotate: None,
+                    span: Some(span),
+                }];
+                let (items, span) = self.list(span, '}');
+                list.extend(items);
+                Expr {
+                    expr: Type::Expr(list),
+                    annotate: None,
+                    span: Some(span),
                 }
             }
-            ' ' | '　' | '\n' | '\t' | '\r' if !in_quote => {
-                if in_parentheses != 0 {
-                    if is_colon {
-                        after_colon.push(c);
-                    } else {
-                        current_token.push(c);
-                    }
-                } else if !current_token.is_empty() {
-                    if is_colon {
-                        is_colon = false;
-                        tokens.push((current_token.clone(), Some(after_colon.clone())));
-                        current_token.clear();
-                        after_colon.clear();
-                    } else {
-                        tokens.push((current_token.clone(), None));
-                        current_token.clear();
+            // List or symbol that explicitly
+            Kind::Quote => match self.peek() {
+                Some(Lexeme {
+                    kind: Kind::Open('('),
+                    ..
+                }) => {
+                    let open = self.next()?.span;
+                    let (list, end) = self.list(open, ')');
+                    Expr {
+                        expr: Type::List(list),
+                        annotate: None,
+                        span: Some(Span {
+                            end: end.end,
+                            ..span
+                        }),
                     }
                 }
-            }
-            ':' if !in_quote => {
-                if in_parentheses != 0 {
-                    if is_colon {
-                        after_colon.push(c);
-                    } else {
-                        current_token.push(c);
+                Some(Lexeme {
+                    kind: Kind::Atom(_),
+                    ..
+                }) => {
+                    let Lexeme {
+                        kind: Kind::Atom(symbol),
+                        span: end,
+                    } = self.next()?
+                    else {
+                        unreachable!()
+                    };
+                    Expr {
+                        expr: Type::Symbol(symbol),
+                        annotate: None,
+                        span: Some(Span {
+                            end: end.end,
+                            ..span
+                        }),
                     }
-                } else {
-                    is_colon = true;
                 }
+                // The quote is ignored and the next form is read as it is
+                _ => {
+                    self.error("quote should be followed by symbol or list", span);
+                    return self.annotated();
+                }
+            },
+            // Reader syntax of quasiquote is the form like `(quasiquote form)`
+            Kind::Quasiquote | Kind::Unquote | Kind::UnquoteSplicing => {
+                let name = match lexeme.kind {
+                    Kind::Quasiquote => "quasiquote",
+                    Kind::Unquote => "unquote",
+                    _ => "unquote-splicing",
+                };
+                let Some(form) = self.form() else {
+                    self.error(&format!("{name} should be followed by a form"), span);
+                    return None;
+                };
+                let span = Span {
+                    end: form.span.unwrap_or(span).end,
+                    ..span
+                };
+                Expr {
+                    expr: Type::Expr(vec![
+                        Expr {
+                            expr: Type::Symbol(name.to_string()),
+                            annotate: None,
+                            span: Some(span),
+                        },
+                        form,
+                    ]),
+                    annotate: None,
+                    span: Some(span),
+                }
+            }
+            // The next form is skipped and kept as trivia
+            Kind::DatumComment => {
+                let Some((skipped, annotation)) = self.annotated() else {
+                    self.error("there's nothing to comment out", span);
+                    return None;
+                };
+                let end = match annotation {
+                    Some(annotation) => annotation.span.end,
+                    None => skipped.span.unwrap_or(span).end,
+                };
+                let comment = Span { end, ..span };
+                self.lexer.trivia.push(Trivia {
+                    source: self.lexer.slice(comment).to_string(),
+                    span: comment,
+                });
+                return self.annotated();
             }
-            '"' => {
-                in_quote = !in_quote;
-                if is_colon {
-                    after_colon.push(c);
-                } else {
-                    current_token.push(c);
+            // Lexer gives annotation only after a value
+            Kind::Close(_) | Kind::Annotation(_) => unreachable!(),
+        };
+
+        let annotation = match self.peek() {
+            Some(Lexeme {
+                kind: Kind::Annotation(_),
+                ..
+            }) => self.next(),
+            _ => None,
+        };
+        Some((expr, annotation))
+    }
+
+    // Parse the forms until the end of the parentheses, and return the span including them
+    fn list(&mut self, open: Span, close: char) -> (Vec<Expr>, Span) {
+        self.depth += 1;
+        let mut list = Vec::new();
+        while let Some(expr) = self.form() {
+            list.push(expr);
+        }
+        self.depth -= 1;
+
+        // Unmatched parentheses are reported, and the list ends there
+        let end = match self.next() {
+            Some(Lexeme {
+                kind: Kind::Close(c),
+                span,
+            }) => {
+                if c != close {
+                    self.error(
+                        &format!("the parentheses should be closed by `{close}`"),
+                        span,
+                    );
                 }
+                span.end
             }
             _ => {
-                if is_colon {
-                    after_colon.push(c);
-                } else {
-                    current_token.push(c);
-                }
+                self.error("there's not end of the parentheses", open);
+                self.lexer.source_end()
             }
-        }
+        };
+        (list, Span { end, ..open })
     }
 
-    // Syntax error check
-    if in_quote {
-        return Err(GradiaError::Syntax(
-            "there's not end of the quote".to_string(),
-        ));
-    }
-    if in_parentheses != 0 {
-        return Err(GradiaError::Syntax(
-            "there's not end of the parentheses".to_string(),
-        ));
+    /// Apply the annotation after the value, the span is extended to include it
+    fn annotate(&mut self, mut expr: Expr, annotation: Option<Lexeme>) -> Expr {
+        if let Some(Lexeme {
+            kind: Kind::Annotation(annotate),
+            span,
+        }) = annotation
+        {
+            expr.span = expr.span.map(|i| Span { end: span.end, ..i });
+            match Class::from(annotate) {
+                Ok(class) => expr.annotate = class,
+                Err(err) => self.lexer.errors.push(err.at(expr.span)),
+            }
+        }
+        expr
     }
+}
 
-    if in_parentheses == 0 && !current_token.is_empty() {
-        if is_colon {
-            tokens.push((current_token.clone(), Some(after_colon.clone())));
-            current_token.clear();
-        } else {
-            tokens.push((current_token.clone(), None));
-            current_token.clear();
-        }
+/// Value of the atom, that is number, bool, null or symbol
+fn literal(atom: String) -> Type {
+    // Integer and fraction case, they're read exactly in any size
+    if let Some(n) = Fraction::from(atom.clone()) {
+        Type::Number(n)
+    // Number case
+    } else if let Some(n) = atom.parse::<f64>().ok().and_then(Fraction::try_new) {
+        Type::Number(n)
+    // Bool calse
+    } else if let Ok(b) = atom.parse::<bool>() {
+        Type::Bool(b)
+    // Null calse
+    } else if atom == "null" {
+        Type::Null
+    // Other case will be symbol
+    } else {
+        Type::Symbol(atom)
     }
-    Ok(tokens)
 }
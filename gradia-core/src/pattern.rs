@@ -0,0 +1,103 @@
+use crate::expr::{Expr, GradiaError};
+use crate::types::{Scope, Type};
+
+/// Bind the variables of the pattern on the scope when the value matches it.
+/// Pattern is `_`, variable, literal, keyword like `:key`, `(quote symbol)` or list like `(a b &rest c)`,
+/// and its annotation like `x:number` is checked
+pub fn matches(pattern: &Expr, value: Type, scope: &Scope) -> Result<bool, GradiaError> {
+    let value = match &pattern.annotate {
+        Some(annotate) => match annotate.check(value, scope) {
+            Ok(value) => value,
+            Err(err @ GradiaError::Syntax(_, _)) => return Err(err),
+            Err(_) => return Ok(false),
+        },
+        None => value,
+    };
+
+    match &pattern.expr {
+        Type::Symbol(name) if name == "_" => Ok(true),
+        Type::Symbol(name) if name.starts_with(':') => Ok(pattern.expr == value),
+        Type::Symbol(name) => {
+            scope.insert(name.clone(), value);
+            Ok(true)
+        }
+        Type::Expr(list) | Type::List(list) => {
+            if let Some(symbol) = quoted(list) {
+                return Ok(symbol.expr == value);
+            }
+            let (Type::List(items) | Type::Expr(items)) = value else {
+                return Ok(false);
+            };
+
+            let (fixed, rest) = match list.iter().position(is_rest) {
+                Some(index) => match &list[index + 1..] {
+                    [rest] => (&list[..index], Some(rest)),
+                    _ => {
+                        return Err(GradiaError::Syntax(
+                            "`&rest` in pattern should be followed by one pattern".to_string(),
+                            list[index].span,
+                        ))
+                    }
+                },
+                None => (&list[..], None),
+            };
+            if items.len() < fixed.len() || (rest.is_none() && items.len() > fixed.len()) {
+                return Ok(false);
+            }
+            for (pattern, item) in fixed.iter().zip(&items) {
+                if !matches(pattern, item.expr.clone(), scope)? {
+                    return Ok(false);
+                }
+            }
+            match rest {
+                Some(rest) => matches(rest, Type::List(items[fixed.len()..].to_vec()), scope),
+                None => Ok(true),
+            }
+        }
+        literal => Ok(*literal == value),
+    }
+}
+
+/// Bind the variables of the pattern, it's error when the value doesn't match
+pub fn destructure(pattern: &Expr, value: Type, scope: &Scope) -> Result<(), GradiaError> {
+    if matches(pattern, value.clone(), scope)? {
+        Ok(())
+    } else {
+        Err(GradiaError::Runtime(
+            format!("the value `{value:?}` doesn't match the pattern `{pattern:?}`"),
+            None,
+        ))
+    }
+}
+
+/// Whether the code is the pattern that destructures list, rather than a variable
+pub fn is_destructuring(pattern: &Expr) -> bool {
+    matches!(&pattern.expr, Type::Expr(list) | Type::List(list) if quoted(list).is_none())
+}
+
+/// Variables that the pattern binds, in the order of the pattern
+pub fn variables(pattern: &Expr) -> Vec<&Expr> {
+    match &pattern.expr {
+        Type::Symbol(name) if name != "_" && !name.starts_with(':') && !name.starts_with('&') => {
+            vec![pattern]
+        }
+        Type::Expr(list) | Type::List(list) if quoted(list).is_none() => {
+            list.iter().flat_map(variables).collect()
+        }
+        _ => vec![],
+    }
+}
+
+// Symbol compared as it is like `(quote name)`
+fn quoted(list: &[Expr]) -> Option<&Expr> {
+    match list {
+        [head, symbol] if matches!(&head.expr, Type::Symbol(name) if name == "quote") => {
+            Some(symbol)
+        }
+        _ => None,
+    }
+}
+
+fn is_rest(pattern: &Expr) -> bool {
+    matches!(&pattern.expr, Type::Symbol(name) if name == "&rest")
+}
@@ -1,103 +1,112 @@
-use crate::expr::{Expr, GradiaError};
+use crate::expr::{self, Expr, GradiaError, Span};
 use crate::fraction::Fraction;
-use crate::types::{Class, Function, Scope, Type};
-use std::collections::HashMap;
+use crate::macros;
+use crate::module;
+use crate::pattern;
+use crate::types::{
+    Class, Entries, ErrorValue, Flow, Function, Lambda, Native, Record, RecordType, Scope, Type,
+};
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, HashMap};
 use std::io::{self, Write};
 use std::process::exit;
+use std::rc::Rc;
 
 pub fn builtin_function() -> Scope {
-    HashMap::from([
+    Scope::from(HashMap::from([
         (
             "+".to_string(),
             Type::Function(Function::BuiltIn(|params, _| {
-                if params.len() >= 1 {
-                    let params: Vec<Fraction> = params.iter().map(|i| i.get_number()).collect();
-                    let mut result: Fraction = params[0];
-                    for i in params[1..params.len()].to_vec().iter() {
-                        result = result + i.clone();
-                    }
-                    Ok(Type::Number(result))
-                } else {
-                    Err(GradiaError::Function(params.len(), 2))
-                }
+                arithmetic(params, |a, b| Ok(a + b), |a, b| a + b)
             })),
         ),
         (
             "-".to_string(),
-            Type::Function(Function::BuiltIn(|params, _| {
-                if params.len() >= 1 {
-                    let params: Vec<Fraction> = params.iter().map(|i| i.get_number()).collect();
-                    if params.len() >= 2 {
-                        let mut result: Fraction = params[0];
-                        for i in params[1..params.len()].to_vec().iter() {
-                            result = result - i.clone();
-                        }
-                        Ok(Type::Number(result))
-                    } else {
-                        Ok(Type::Number(Fraction::new(0.0) - params[0]))
-                    }
-                } else {
-                    Err(GradiaError::Function(params.len(), 2))
+            Type::Function(Function::BuiltIn(|mut params, _| {
+                if params.len() == 1 {
+                    params.insert(0, Type::Number(Fraction::new(0.0)));
                 }
+                arithmetic(params, |a, b| Ok(a - b), |a, b| a - b)
             })),
         ),
         (
             "*".to_string(),
             Type::Function(Function::BuiltIn(|params, _| {
-                if params.len() >= 1 {
-                    let params: Vec<Fraction> = params.iter().map(|i| i.get_number()).collect();
-                    let mut result: Fraction = params[0];
-                    for i in params[1..params.len()].to_vec().iter() {
-                        result = result * i.clone();
-                    }
-                    Ok(Type::Number(result))
-                } else {
-                    Err(GradiaError::Function(params.len(), 2))
-                }
+                arithmetic(params, |a, b| Ok(a * b), |a, b| a * b)
             })),
         ),
         (
             "/".to_string(),
             Type::Function(Function::BuiltIn(|params, _| {
-                if params.len() >= 1 {
-                    let params: Vec<Fraction> = params.iter().map(|i| i.get_number()).collect();
-                    let mut result: Fraction = params[0];
-                    for i in params[1..params.len()].to_vec().iter() {
-                        result = result / i.clone();
-                    }
-                    Ok(Type::Number(result))
-                } else {
-                    Err(GradiaError::Function(params.len(), 2))
-                }
+                arithmetic(
+                    params,
+                    |a, b| {
+                        if b.is_zero() {
+                            Err(GradiaError::Runtime("division by zero".to_string(), None))
+                        } else {
+                            Ok(a / b)
+                        }
+                    },
+                    |a, b| a / b,
+                )
             })),
         ),
         (
             "%".to_string(),
             Type::Function(Function::BuiltIn(|params, _| {
-                if params.len() >= 1 {
-                    let params: Vec<f64> = params.iter().map(|i| i.get_number().to_f64()).collect();
-                    let mut result: f64 = params[0];
-                    for i in params[1..params.len()].to_vec().iter() {
-                        result %= i;
-                    }
-                    Ok(Type::Number(Fraction::new(result)))
+                arithmetic(
+                    params,
+                    |a, b| {
+                        if b.is_zero() {
+                            Err(GradiaError::Runtime("division by zero".to_string(), None))
+                        } else {
+                            Ok(a % b)
+                        }
+                    },
+                    |a, b| a % b,
+                )
+            })),
+        ),
+        (
+            "^".to_string(),
+            Type::Function(Function::BuiltIn(|params, _| {
+                // Exact number raised to integer power stays exact, otherwise it's float
+                let exact = params.iter().skip(1).all(|i| match i {
+                    Type::Float(_) => false,
+                    other => other.get_number().is_ok_and(|i| i.to_i32().is_some()),
+                });
+                let params = if exact {
+                    params
+                } else {
+                    params.iter().map(|i| Type::Float(i.get_float())).collect()
+                };
+                arithmetic(
+                    params,
+                    |a, b| {
+                        a.pow(b.to_i32().unwrap_or_default())
+                            .map_err(|err| GradiaError::Runtime(err.to_string(), None))
+                    },
+                    f64::powf,
+                )
+            })),
+        ),
+        (
+            "exact->inexact".to_string(),
+            Type::Function(Function::BuiltIn(|params, _| {
+                if params.len() == 1 {
+                    Ok(Type::Float(params[0].get_float()))
                 } else {
-                    Err(GradiaError::Function(params.len(), 2))
+                    Err(GradiaError::Function(params.len(), 1, None))
                 }
             })),
         ),
         (
-            "^".to_string(),
+            "inexact->exact".to_string(),
             Type::Function(Function::BuiltIn(|params, _| {
-                if params.len() >= 1 {
-                    let params: Vec<f64> = params.iter().map(|i| i.get_number().to_f64()).collect();
-                    let mut result: f64 = params[0];
-                    for i in params[1..params.len()].to_vec().iter() {
-                        result = result.powf(i.to_owned());
-                    }
-                    Ok(Type::Number(Fraction::new(result)))
+                if params.len() == 1 {
+                    Ok(Type::Number(params[0].get_number()?))
                 } else {
-                    Err(GradiaError::Function(params.len(), 2))
+                    Err(GradiaError::Function(params.len(), 1, None))
                 }
             })),
         ),
@@ -142,7 +151,7 @@ pub fn builtin_function() -> Scope {
                 if params.len() <= 1 {
                     Ok(Type::String({
                         let mut input = String::new();
-                        if let Some(prompt) = params.get(0) {
+                        if let Some(prompt) = params.first() {
                             print!("{}", prompt.get_string());
                         }
                         io::stdout().flush().unwrap_or_default();
@@ -151,25 +160,26 @@ pub fn builtin_function() -> Scope {
                             Err(_) => {
                                 return Err(GradiaError::Runtime(
                                     "reading line was fault".to_string(),
+                                    None,
                                 ))
                             }
                         }
                     }))
                 } else {
-                    Err(GradiaError::Function(params.len(), 1))
+                    Err(GradiaError::Function(params.len(), 1, None))
                 }
             })),
         ),
         (
             "=".to_string(),
             Type::Function(Function::BuiltIn(|params, _| {
+                // Numbers are compared by the value regardless of exactness
                 if params.len() >= 2 {
-                    Ok(Type::Bool({
-                        let params: Vec<String> = params.iter().map(|i| format!("{i:?}")).collect();
-                        params.windows(2).all(|window| window[0] == window[1])
-                    }))
+                    Ok(Type::Bool(params.windows(2).all(|window| {
+                        window[0].partial_cmp(&window[1]) == Some(Ordering::Equal)
+                    })))
                 } else {
-                    Err(GradiaError::Function(params.len(), 2))
+                    Err(GradiaError::Function(params.len(), 2, None))
                 }
             })),
         ),
@@ -177,69 +187,62 @@ pub fn builtin_function() -> Scope {
             "!=".to_string(),
             Type::Function(Function::BuiltIn(|params, _| {
                 if params.len() >= 2 {
-                    Ok(Type::Bool({
-                        let params: Vec<String> = params.iter().map(|i| format!("{i:?}")).collect();
-                        params.windows(2).all(|window| window[0] != window[1])
-                    }))
+                    Ok(Type::Bool(params.windows(2).all(|window| {
+                        window[0].partial_cmp(&window[1]) != Some(Ordering::Equal)
+                    })))
                 } else {
-                    Err(GradiaError::Function(params.len(), 2))
+                    Err(GradiaError::Function(params.len(), 2, None))
                 }
             })),
         ),
         (
-            ">".to_string(),
+            "equal?".to_string(),
             Type::Function(Function::BuiltIn(|params, _| {
                 if params.len() >= 2 {
-                    Ok(Type::Bool({
-                        let params: Vec<f64> =
-                            params.iter().map(|i| i.get_number().to_f64()).collect();
-                        params.windows(2).all(|window| window[0] > window[1])
-                    }))
+                    Ok(Type::Bool(
+                        params.windows(2).all(|window| window[0] == window[1]),
+                    ))
                 } else {
-                    Err(GradiaError::Function(params.len(), 2))
+                    Err(GradiaError::Function(params.len(), 2, None))
                 }
             })),
         ),
         (
-            ">=".to_string(),
+            "eq?".to_string(),
             Type::Function(Function::BuiltIn(|params, _| {
                 if params.len() >= 2 {
-                    Ok(Type::Bool({
-                        let params: Vec<f64> =
-                            params.iter().map(|i| i.get_number().to_f64()).collect();
-                        params.windows(2).all(|window| window[0] >= window[1])
-                    }))
+                    Ok(Type::Bool(
+                        params
+                            .windows(2)
+                            .all(|window| window[0].is_identical(&window[1])),
+                    ))
                 } else {
-                    Err(GradiaError::Function(params.len(), 2))
+                    Err(GradiaError::Function(params.len(), 2, None))
                 }
             })),
         ),
+        (
+            ">".to_string(),
+            Type::Function(Function::BuiltIn(|params, _| {
+                compare(params, Ordering::is_gt)
+            })),
+        ),
+        (
+            ">=".to_string(),
+            Type::Function(Function::BuiltIn(|params, _| {
+                compare(params, Ordering::is_ge)
+            })),
+        ),
         (
             "<".to_string(),
             Type::Function(Function::BuiltIn(|params, _| {
-                if params.len() >= 2 {
-                    Ok(Type::Bool({
-                        let params: Vec<f64> =
-                            params.iter().map(|i| i.get_number().to_f64()).collect();
-                        params.windows(2).all(|window| window[0] < window[1])
-                    }))
-                } else {
-                    Err(GradiaError::Function(params.len(), 2))
-                }
+                compare(params, Ordering::is_lt)
             })),
         ),
         (
             "<=".to_string(),
             Type::Function(Function::BuiltIn(|params, _| {
-                if params.len() >= 2 {
-                    Ok(Type::Bool({
-                        let params: Vec<f64> =
-                            params.iter().map(|i| i.get_number().to_f64()).collect();
-                        params.windows(2).all(|window| window[0] < window[1])
-                    }))
-                } else {
-                    Err(GradiaError::Function(params.len(), 2))
-                }
+                compare(params, Ordering::is_le)
             })),
         ),
         (
@@ -251,7 +254,7 @@ pub fn builtin_function() -> Scope {
                         params.iter().all(|x| *x)
                     }))
                 } else {
-                    Err(GradiaError::Function(params.len(), 2))
+                    Err(GradiaError::Function(params.len(), 2, None))
                 }
             })),
         ),
@@ -264,8 +267,30 @@ pub fn builtin_function() -> Scope {
                         params.iter().any(|x| *x)
                     }))
                 } else {
-                    Err(GradiaError::Function(params.len(), 2))
+                    Err(GradiaError::Function(params.len(), 2, None))
+                }
+            })),
+        ),
+        (
+            "and".to_string(),
+            Type::Function(Function::Special(|params, scope| {
+                for i in params {
+                    if !i.eval(scope)?.get_bool() {
+                        return Ok(Flow::Value(Type::Bool(false)));
+                    }
+                }
+                Ok(Flow::Value(Type::Bool(true)))
+            })),
+        ),
+        (
+            "or".to_string(),
+            Type::Function(Function::Special(|params, scope| {
+                for i in params {
+                    if i.eval(scope)?.get_bool() {
+                        return Ok(Flow::Value(Type::Bool(true)));
+                    }
                 }
+                Ok(Flow::Value(Type::Bool(false)))
             })),
         ),
         (
@@ -274,20 +299,20 @@ pub fn builtin_function() -> Scope {
                 if params.len() == 1 {
                     Ok(Type::Bool(!params[0].get_bool()))
                 } else {
-                    Err(GradiaError::Function(params.len(), 1))
+                    Err(GradiaError::Function(params.len(), 1, None))
                 }
             })),
         ),
         (
             "cast".to_string(),
-            Type::Function(Function::BuiltIn(|params, _| {
+            Type::Function(Function::BuiltIn(|params, scope| {
                 if params.len() == 2 {
                     Ok(match Class::from(params[1].get_string())? {
-                        Some(typed) => typed.parse(params[0].clone()),
+                        Some(typed) => typed.parse(params[0].clone(), scope)?,
                         None => params[0].clone(),
                     })
                 } else {
-                    Err(GradiaError::Function(params.len(), 2))
+                    Err(GradiaError::Function(params.len(), 2, None))
                 }
             })),
         ),
@@ -297,7 +322,7 @@ pub fn builtin_function() -> Scope {
                 if params.len() == 1 {
                     Ok(Type::String(params[0].get_type()))
                 } else {
-                    Err(GradiaError::Function(params.len(), 1))
+                    Err(GradiaError::Function(params.len(), 1, None))
                 }
             })),
         ),
@@ -309,6 +334,7 @@ pub fn builtin_function() -> Scope {
                     result = Expr {
                         expr: Type::Expr(expr.get_list()),
                         annotate: None,
+                        span: None,
                     }
                     .eval(scope)?;
                 }
@@ -317,100 +343,460 @@ pub fn builtin_function() -> Scope {
         ),
         (
             "define".to_string(),
-            Type::Function(Function::BuiltIn(|params, scope| {
-                let value: Type;
+            Type::Function(Function::Special(|params, scope| {
                 if params.len() >= 2 {
-                    if let Type::List(args) = params[0].clone() {
-                        value = Type::Function(Function::UserDefined(
-                            args[1..].to_vec(),
-                            params[1..].to_owned(),
-                        ));
-                        scope.insert(args[0].expr.get_string(), value.clone());
+                    if let Type::Expr(header) | Type::List(header) = &params[0].expr {
+                        // Function definition like `(define (name params...) body...)` or `(define '(name params...) body...)`
+                        let Some(name) = header.first() else {
+                            return Err(GradiaError::Syntax(
+                                "function definition needs its name".to_string(),
+                                params[0].span,
+                            ));
+                        };
+                        let value = Type::Function(Function::UserDefined(Rc::new(Lambda {
+                            params: header[1..].to_vec(),
+                            result: params[0].annotate.clone(),
+                            body: params[1..].to_vec(),
+                            scope: scope.clone(),
+                        })));
+                        scope.insert(name.expr.get_string(), value.clone());
+                        Ok(Flow::Value(value))
                     } else {
-                        value = params[1].to_owned();
-                        scope.insert(params[0].get_string(), value.clone());
+                        let value = bind_value(&params[0], &params[1], scope)?;
+                        scope.insert(params[0].expr.get_string(), value.clone());
+                        Ok(Flow::Value(value))
                     }
                 } else {
-                    return Err(GradiaError::Function(params.len(), 2));
+                    Err(GradiaError::Function(params.len(), 2, None))
                 }
-                Ok(value)
             })),
         ),
         (
-            "lambda".to_string(),
-            Type::Function(Function::BuiltIn(|params, _| {
-                if params.len() >= 2 {
-                    Ok(Type::Function(Function::UserDefined(
-                        params[0].get_list(),
-                        params[1..].to_vec(),
-                    )))
+            "define-values".to_string(),
+            Type::Function(Function::Special(|params, scope| {
+                // List pattern like `(define-values (a b &rest c) value)` destructures the value
+                if params.len() == 2 {
+                    if !pattern::is_destructuring(&params[0]) {
+                        return Err(GradiaError::Syntax(
+                            "define-values needs list pattern like `(a b &rest c)`".to_string(),
+                            params[0].span,
+                        ));
+                    }
+                    let value = bind_value(&params[0], &params[1], scope)?;
+                    pattern::destructure(&params[0], value.clone(), scope)
+                        .map_err(|err| err.at(params[1].span))?;
+                    Ok(Flow::Value(value))
                 } else {
-                    Err(GradiaError::Function(params.len(), 2))
+                    Err(GradiaError::Function(params.len(), 2, None))
                 }
             })),
         ),
         (
-            "if".to_string(),
-            Type::Function(Function::BuiltIn(|params, scope| {
-                if params.len() == 3 {
-                    if params[0].get_bool() {
-                        if let Type::List(expr) = params[1].clone() {
-                            Expr {
-                                expr: Type::Expr(expr),
-                                annotate: None,
+            "defrecord".to_string(),
+            Type::Function(Function::Special(|params, scope| {
+                // Record definition like `(defrecord name field:type...)`
+                let Some((name, fields)) = params.split_first() else {
+                    return Err(GradiaError::Function(params.len(), 1, None));
+                };
+                let name = name.expr.get_string();
+                // Prefix like `prefix/name` is only for the imported records
+                if name.contains('/')
+                    || !matches!(Class::from(name.clone()), Ok(Some(Class::Record(_))))
+                {
+                    return Err(GradiaError::Syntax(
+                        format!("`{name}` can't be used as name of record"),
+                        params[0].span,
+                    ));
+                }
+                let kind = Rc::new(RecordType {
+                    name,
+                    fields: fields
+                        .iter()
+                        .map(|i| (i.expr.get_string(), i.annotate.clone()))
+                        .collect(),
+                });
+                scope.insert_record(kind.name.clone(), kind.clone());
+
+                // Annotations of the fields are resolved where the record is defined
+                let constructor = kind.clone();
+                let definition = scope.clone();
+                scope.insert(
+                    format!("make-{}", kind.name),
+                    native(format!("make-{}", kind.name), move |params| {
+                        if params.len() != constructor.fields.len() {
+                            return Err(GradiaError::Function(
+                                params.len(),
+                                constructor.fields.len(),
+                                None,
+                            ));
+                        }
+                        let mut values = vec![];
+                        for ((_, annotate), value) in constructor.fields.iter().zip(params) {
+                            values.push(match annotate {
+                                Some(annotate) => annotate.check(value, &definition)?,
+                                None => value,
+                            });
+                        }
+                        Ok(Type::Record(Rc::new(Record {
+                            kind: constructor.clone(),
+                            values,
+                        })))
+                    }),
+                );
+
+                for (index, (field, _)) in kind.fields.iter().enumerate() {
+                    let accessor = kind.clone();
+                    scope.insert(
+                        format!("{}-{field}", kind.name),
+                        native(format!("{}-{field}", kind.name), move |params| match params
+                            .as_slice()
+                        {
+                            [Type::Record(record)] if Rc::ptr_eq(&record.kind, &accessor) => {
+                                Ok(record.values[index].clone())
                             }
-                            .eval(scope)
-                        } else {
-                            Ok(params[1].clone())
+                            [other] => Err(GradiaError::Type(
+                                other.clone(),
+                                Class::Record(accessor.name.clone()).describe(),
+                                None,
+                            )),
+                            _ => Err(GradiaError::Function(params.len(), 1, None)),
+                        }),
+                    );
+                }
+
+                let predicate = kind.clone();
+                scope.insert(
+                    format!("{}?", kind.name),
+                    native(format!("{}?", kind.name), move |params| {
+                        match params.as_slice() {
+                            [value] => Ok(Type::Bool(matches!(
+                                value,
+                                Type::Record(record) if Rc::ptr_eq(&record.kind, &predicate)
+                            ))),
+                            _ => Err(GradiaError::Function(params.len(), 1, None)),
                         }
+                    }),
+                );
+                Ok(Flow::Value(Type::Null))
+            })),
+        ),
+        (
+            "set!".to_string(),
+            Type::Function(Function::Special(|params, scope| {
+                if params.len() == 2 {
+                    let value = bind_value(&params[0], &params[1], scope)?;
+                    let name = params[0].expr.get_string();
+                    if scope.set(&name, value.clone()) {
+                        Ok(Flow::Value(value))
                     } else {
-                        if let Type::List(expr) = params[2].clone() {
-                            Expr {
-                                expr: Type::Expr(expr),
-                                annotate: None,
+                        Err(GradiaError::Runtime(
+                            format!("variable `{name}` is not defined"),
+                            params[0].span,
+                        ))
+                    }
+                } else {
+                    Err(GradiaError::Function(params.len(), 2, None))
+                }
+            })),
+        ),
+        (
+            "let".to_string(),
+            Type::Function(Function::Special(|params, scope| {
+                if !params.is_empty() {
+                    // All values are evaluated before any of the variables are bound
+                    let mut values = vec![];
+                    for binding in params[0].expr.get_list() {
+                        let binding = binding.expr.get_list();
+                        if binding.len() != 2 {
+                            return Err(GradiaError::Function(binding.len(), 2, None));
+                        }
+                        values.push((
+                            binding[0].clone(),
+                            bind_value(&binding[0], &binding[1], scope)?,
+                            binding[1].span,
+                        ));
+                    }
+
+                    let mut block_scope = scope.child();
+                    for (name, value, span) in values {
+                        bind_pattern(&name, value, &block_scope).map_err(|err| err.at(span))?;
+                    }
+                    match params[1..].split_last() {
+                        Some((last, lines)) => {
+                            for line in lines {
+                                line.eval(&mut block_scope)?;
                             }
-                            .eval(scope)
-                        } else {
-                            Ok(params[2].clone())
+                            Ok(Flow::Tail(last, block_scope))
                         }
+                        None => Ok(Flow::Value(Type::Null)),
                     }
-                } else if params.len() == 2 {
-                    if params[0].get_bool() {
-                        if let Type::List(expr) = params[1].clone() {
-                            Expr {
-                                expr: Type::Expr(expr),
-                                annotate: None,
+                } else {
+                    Err(GradiaError::Function(params.len(), 2, None))
+                }
+            })),
+        ),
+        (
+            "let*".to_string(),
+            Type::Function(Function::Special(|params, scope| {
+                if !params.is_empty() {
+                    // Each value can refer to the variables bound before it
+                    let mut block_scope = scope.child();
+                    for binding in params[0].expr.get_list() {
+                        let binding = binding.expr.get_list();
+                        if binding.len() != 2 {
+                            return Err(GradiaError::Function(binding.len(), 2, None));
+                        }
+                        let value = bind_value(&binding[0], &binding[1], &mut block_scope)?;
+                        bind_pattern(&binding[0], value, &block_scope)
+                            .map_err(|err| err.at(binding[1].span))?;
+                    }
+
+                    match params[1..].split_last() {
+                        Some((last, lines)) => {
+                            for line in lines {
+                                line.eval(&mut block_scope)?;
                             }
-                            .eval(scope)
-                        } else {
-                            Ok(params[1].clone())
+                            Ok(Flow::Tail(last, block_scope))
                         }
+                        None => Ok(Flow::Value(Type::Null)),
+                    }
+                } else {
+                    Err(GradiaError::Function(params.len(), 2, None))
+                }
+            })),
+        ),
+        (
+            "lambda".to_string(),
+            Type::Function(Function::Special(|params, scope| {
+                if params.len() >= 2 {
+                    Ok(Flow::Value(Type::Function(Function::UserDefined(Rc::new(
+                        Lambda {
+                            params: params[0].expr.get_list(),
+                            result: params[0].annotate.clone(),
+                            body: params[1..].to_vec(),
+                            scope: scope.clone(),
+                        },
+                    )))))
+                } else {
+                    Err(GradiaError::Function(params.len(), 2, None))
+                }
+            })),
+        ),
+        (
+            "import".to_string(),
+            Type::Function(Function::Special(|params, scope| {
+                let Some((path, options)) = params.split_first() else {
+                    return Err(GradiaError::Function(0, 1, None));
+                };
+                let exports = module::import(&path.eval(scope)?.get_string(), scope)
+                    .map_err(|err| err.at(path.span))?;
+
+                let options = module::Options::parse(options)?;
+                let exported: Vec<&str> = (exports.values.iter().map(|(i, _)| i.as_str()))
+                    .chain(exports.records.iter().map(|(i, _)| i.as_str()))
+                    .collect();
+                if let Some(name) = options.missing(&exported).first() {
+                    return Err(GradiaError::Runtime(
+                        format!("`{name}` isn't exported by `{}`", path.expr.get_string()),
+                        path.span,
+                    ));
+                }
+                for (name, value) in exports.values.iter() {
+                    if let Some(name) = options.name(name) {
+                        scope.insert(name, value.clone());
+                    }
+                }
+                for (name, kind) in exports.records.iter() {
+                    if let Some(name) = options.name(name) {
+                        scope.insert_record(name, kind.clone());
+                    }
+                }
+                Ok(Flow::Value(Type::Null))
+            })),
+        ),
+        (
+            "export".to_string(),
+            Type::Function(Function::Special(|params, scope| {
+                let Some(module) = scope.module() else {
+                    return Err(GradiaError::Runtime(
+                        "export is available only in file of the program".to_string(),
+                        None,
+                    ));
+                };
+                module
+                    .exports
+                    .borrow_mut()
+                    .extend(params.iter().map(|i| i.expr.get_string()));
+                Ok(Flow::Value(Type::Null))
+            })),
+        ),
+        (
+            "defmacro".to_string(),
+            Type::Function(Function::Special(|params, scope| {
+                if params.len() >= 3 {
+                    let value = Type::Function(Function::Macro(Rc::new(Lambda {
+                        params: params[1].expr.get_list(),
+                        result: params[1].annotate.clone(),
+                        body: params[2..].to_vec(),
+                        scope: scope.clone(),
+                    })));
+                    scope.insert(params[0].expr.get_string(), value);
+                    Ok(Flow::Value(Type::Null))
+                } else {
+                    Err(GradiaError::Function(params.len(), 3, None))
+                }
+            })),
+        ),
+        (
+            "quasiquote".to_string(),
+            Type::Function(Function::Special(|params, scope| {
+                if params.len() == 1 {
+                    // Template of list is the data like quoted list
+                    Ok(Flow::Value(
+                        match macros::quasiquote(&params[0], scope)?.expr {
+                            Type::Expr(list) => Type::List(list),
+                            other => other,
+                        },
+                    ))
+                } else {
+                    Err(GradiaError::Function(params.len(), 1, None))
+                }
+            })),
+        ),
+        (
+            "unquote".to_string(),
+            Type::Function(Function::Special(|_, _| {
+                Err(GradiaError::Syntax(
+                    "unquote should be inside of quasiquote".to_string(),
+                    None,
+                ))
+            })),
+        ),
+        (
+            "unquote-splicing".to_string(),
+            Type::Function(Function::Special(|_, _| {
+                Err(GradiaError::Syntax(
+                    "unquote-splicing should be inside of list of quasiquote".to_string(),
+                    None,
+                ))
+            })),
+        ),
+        (
+            "gensym".to_string(),
+            Type::Function(Function::BuiltIn(|params, _| match params.as_slice() {
+                [] => Ok(macros::gensym("g")),
+                [prefix] => Ok(macros::gensym(&prefix.get_string())),
+                _ => Err(GradiaError::Function(params.len(), 1, None)),
+            })),
+        ),
+        (
+            "macroexpand".to_string(),
+            Type::Function(Function::BuiltIn(|params, scope| {
+                if params.len() == 1 {
+                    let code = macros::to_code(params[0].clone(), None);
+                    Ok(match macros::expand(&code, scope)?.expr {
+                        Type::Expr(list) => Type::List(list),
+                        other => other,
+                    })
+                } else {
+                    Err(GradiaError::Function(params.len(), 1, None))
+                }
+            })),
+        ),
+        (
+            "if".to_string(),
+            Type::Function(Function::Special(|params, scope| {
+                if params.len() == 3 {
+                    if params[0].eval(scope)?.get_bool() {
+                        Ok(Flow::Tail(&params[1], scope.clone()))
+                    } else {
+                        Ok(Flow::Tail(&params[2], scope.clone()))
+                    }
+                } else if params.len() == 2 {
+                    if params[0].eval(scope)?.get_bool() {
+                        Ok(Flow::Tail(&params[1], scope.clone()))
                     } else {
-                        Ok(Type::Null)
+                        Ok(Flow::Value(Type::Null))
                     }
                 } else {
-                    Err(GradiaError::Function(params.len(), 3))
+                    Err(GradiaError::Function(params.len(), 3, None))
                 }
             })),
         ),
         (
             "cond".to_string(),
-            Type::Function(Function::BuiltIn(|params, scope| {
-                for i in params {
-                    if i.get_list()[0].eval(scope)?.get_bool() {
-                        let code = i.get_list()[1].eval(scope)?;
-                        return if let Type::List(expr) = code {
-                            Expr {
-                                expr: Type::Expr(expr),
-                                annotate: None,
+            Type::Function(Function::Special(|params, scope| {
+                for clause in params {
+                    // Each clause is `(condition code...)`
+                    let (Type::Expr(clause) | Type::List(clause)) = &clause.expr else {
+                        continue;
+                    };
+                    let Some((condition, code)) = clause.split_first() else {
+                        continue;
+                    };
+                    if condition.eval(scope)?.get_bool() {
+                        return match code.split_last() {
+                            Some((last, lines)) => {
+                                for line in lines {
+                                    line.eval(scope)?;
+                                }
+                                Ok(Flow::Tail(last, scope.clone()))
                             }
-                            .eval(scope)
-                        } else {
-                            Ok(code.clone())
+                            None => Ok(Flow::Value(Type::Null)),
                         };
                     }
                 }
-                Ok(Type::Null)
+                Ok(Flow::Value(Type::Null))
+            })),
+        ),
+        (
+            "match".to_string(),
+            Type::Function(Function::Special(|params, scope| {
+                let Some((target, clauses)) = params.split_first() else {
+                    return Err(GradiaError::Function(0, 1, None));
+                };
+                let value = target.eval(scope)?;
+                for clause in clauses {
+                    // Each clause is `(pattern code...)` or `(pattern :when guard code...)`
+                    let Some((pattern, mut code)) = (match &clause.expr {
+                        Type::Expr(clause) | Type::List(clause) => clause.split_first(),
+                        _ => None,
+                    }) else {
+                        return Err(GradiaError::Syntax(
+                            "match clause should be like `(pattern code...)`".to_string(),
+                            clause.span,
+                        ));
+                    };
+                    let mut clause_scope = scope.child();
+                    if !pattern::matches(pattern, value.clone(), &clause_scope)? {
+                        continue;
+                    }
+                    if let [Expr {
+                        expr: Type::Symbol(keyword),
+                        ..
+                    }, guard, rest @ ..] = code
+                    {
+                        if keyword == ":when" {
+                            if !guard.eval(&mut clause_scope)?.get_bool() {
+                                continue;
+                            }
+                            code = rest;
+                        }
+                    }
+                    return match code.split_last() {
+                        Some((last, lines)) => {
+                            for line in lines {
+                                line.eval(&mut clause_scope)?;
+                            }
+                            Ok(Flow::Tail(last, clause_scope))
+                        }
+                        None => Ok(Flow::Value(Type::Null)),
+                    };
+                }
+                Err(GradiaError::Runtime(
+                    format!("there's no pattern that matches `{value:?}`"),
+                    None,
+                ))
             })),
         ),
         (
@@ -419,13 +805,13 @@ pub fn builtin_function() -> Scope {
                 if params.len() == 1 {
                     Ok(params[0]
                         .get_list()
-                        .get(0)
+                        .first()
                         .cloned()
                         .unwrap_or_default()
                         .expr
                         .clone())
                 } else {
-                    Err(GradiaError::Function(params.len(), 1))
+                    Err(GradiaError::Function(params.len(), 1, None))
                 }
             })),
         ),
@@ -438,49 +824,55 @@ pub fn builtin_function() -> Scope {
                         list.get(1..list.len()).unwrap_or_default().to_vec(),
                     ))
                 } else {
-                    Err(GradiaError::Function(params.len(), 1))
+                    Err(GradiaError::Function(params.len(), 1, None))
                 }
             })),
         ),
         (
             "range".to_string(),
             Type::Function(Function::BuiltIn(|params, _| {
-                if params.len() == 1 {
-                    let mut range: Vec<Expr> = vec![];
-                    let mut current: f64 = 0.0;
-                    while current < params[0].get_number().to_f64() {
-                        range.push(Expr {
-                            expr: Type::Number(Fraction::new(current)),
-                            annotate: None,
-                        });
-                        current += 1.0;
-                    }
-                    Ok(Type::List(range))
-                } else if params.len() == 2 {
-                    let mut range: Vec<Expr> = vec![];
-                    let mut current: f64 = params[0].get_number().to_f64();
-                    while current < params[1].get_number().to_f64() {
-                        range.push(Expr {
-                            expr: Type::Number(Fraction::new(current)),
-                            annotate: None,
-                        });
-                        current += 1.0;
-                    }
-                    Ok(Type::List(range))
-                } else if params.len() == 3 {
-                    let mut range: Vec<Expr> = vec![];
-                    let mut current: f64 = params[0].get_number().to_f64();
-                    while current < params[1].get_number().to_f64() {
-                        range.push(Expr {
-                            expr: Type::Number(Fraction::new(current)),
-                            annotate: None,
-                        });
-                        current += params[2].get_number().to_f64();
-                    }
-                    Ok(Type::List(range))
+                // Steps are added exactly, so that fractional step doesn't drift
+                let (start, end, step) = match params.len() {
+                    1 => (
+                        Fraction::new(0.0),
+                        params[0].get_number()?,
+                        Fraction::new(1.0),
+                    ),
+                    2 => (
+                        params[0].get_number()?,
+                        params[1].get_number()?,
+                        Fraction::new(1.0),
+                    ),
+                    3 => (
+                        params[0].get_number()?,
+                        params[1].get_number()?,
+                        params[2].get_number()?,
+                    ),
+                    _ => return Err(GradiaError::Function(params.len(), 3, None)),
+                };
+                if step.is_zero() {
+                    return Err(GradiaError::Runtime(
+                        "step of range can't be zero".to_string(),
+                        None,
+                    ));
+                }
+                // Negative step counts down to the end
+                let ascending = step > Fraction::new(0.0);
+                let mut range: Vec<Expr> = vec![];
+                let mut current = start;
+                while if ascending {
+                    current < end
                 } else {
-                    Err(GradiaError::Function(params.len(), 3))
+                    current > end
+                } {
+                    range.push(Expr {
+                        expr: Type::Number(current.clone()),
+                        annotate: None,
+                        span: None,
+                    });
+                    current = current + step.clone();
                 }
+                Ok(Type::List(range))
             })),
         ),
         (
@@ -494,16 +886,18 @@ pub fn builtin_function() -> Scope {
                                 Expr {
                                     expr: func.clone(),
                                     annotate: None,
+                                    span: None,
                                 },
                                 i,
                             ]),
                             annotate: None,
+                            span: None,
                         }
                         .eval(scope)?;
                     }
                     Ok(Type::Null)
                 } else {
-                    Err(GradiaError::Function(params.len(), 2))
+                    Err(GradiaError::Function(params.len(), 2, None))
                 }
             })),
         ),
@@ -520,18 +914,21 @@ pub fn builtin_function() -> Scope {
                                     Expr {
                                         expr: func.clone(),
                                         annotate: None,
+                                        span: None,
                                     },
                                     i,
                                 ]),
                                 annotate: None,
+                                span: None,
                             }
                             .eval(scope)?,
                             annotate: None,
+                            span: None,
                         });
                     }
                     Ok(Type::List(result))
                 } else {
-                    Err(GradiaError::Function(params.len(), 2))
+                    Err(GradiaError::Function(params.len(), 2, None))
                 }
             })),
         ),
@@ -547,10 +944,12 @@ pub fn builtin_function() -> Scope {
                                 Expr {
                                     expr: func.to_owned(),
                                     annotate: None,
+                                    span: None,
                                 },
                                 i.clone(),
                             ]),
                             annotate: None,
+                            span: None,
                         })
                         .eval(scope)?
                         .get_bool()
@@ -560,7 +959,7 @@ pub fn builtin_function() -> Scope {
                     }
                     Ok(Type::List(result))
                 } else {
-                    Err(GradiaError::Function(params.len(), 2))
+                    Err(GradiaError::Function(params.len(), 2, None))
                 }
             })),
         ),
@@ -570,12 +969,14 @@ pub fn builtin_function() -> Scope {
                 if params.len() == 2 {
                     let func = params[1].clone();
                     let list = params[0].get_list();
-                    let mut result = if let Some(first) = list.get(0) {
+                    let mut result = if let Some(first) = list.first() {
                         first.expr.clone()
                     } else {
-                        return Err(GradiaError::Runtime("passed list is empty".to_string()));
+                        return Err(GradiaError::Runtime(
+                            "passed list is empty".to_string(),
+                            None,
+                        ));
                     };
-                    let mut scope = scope.clone();
 
                     for i in list.get(1..).unwrap_or_default() {
                         result = Expr {
@@ -583,20 +984,152 @@ pub fn builtin_function() -> Scope {
                                 Expr {
                                     expr: func.clone(),
                                     annotate: None,
+                                    span: None,
                                 },
                                 Expr {
                                     expr: result,
                                     annotate: None,
+                                    span: None,
                                 },
                                 i.clone(),
                             ]),
                             annotate: None,
+                            span: None,
                         }
-                        .eval(&mut scope)?
+                        .eval(scope)?
                     }
                     Ok(result.to_owned())
                 } else {
-                    Err(GradiaError::Function(params.len(), 2))
+                    Err(GradiaError::Function(params.len(), 2, None))
+                }
+            })),
+        ),
+        (
+            "hash-map".to_string(),
+            Type::Function(Function::BuiltIn(|params, _| {
+                if params.len() % 2 == 0 {
+                    let mut entries = BTreeMap::new();
+                    for pair in params.chunks(2) {
+                        entries.insert(pair[0].get_key(), (pair[0].clone(), pair[1].clone()));
+                    }
+                    Ok(Type::Map(Rc::new(entries)))
+                } else {
+                    Err(GradiaError::Function(params.len(), params.len() + 1, None))
+                }
+            })),
+        ),
+        (
+            "get".to_string(),
+            Type::Function(Function::BuiltIn(|params, _| {
+                // Default value is returned if the key isn't in the map
+                if params.len() == 2 || params.len() == 3 {
+                    let entries = get_map(&params[0])?;
+                    Ok(match entries.get(&params[1].get_key()) {
+                        Some((_, value)) => value.clone(),
+                        None => params.get(2).cloned().unwrap_or_default(),
+                    })
+                } else {
+                    Err(GradiaError::Function(params.len(), 2, None))
+                }
+            })),
+        ),
+        (
+            "assoc".to_string(),
+            Type::Function(Function::BuiltIn(|params, _| {
+                if params.len() >= 3 && params.len() % 2 == 1 {
+                    let mut entries = get_map(&params[0])?;
+                    let map = Rc::make_mut(&mut entries);
+                    for pair in params[1..].chunks(2) {
+                        map.insert(pair[0].get_key(), (pair[0].clone(), pair[1].clone()));
+                    }
+                    Ok(Type::Map(entries))
+                } else {
+                    Err(GradiaError::Function(params.len(), 3, None))
+                }
+            })),
+        ),
+        (
+            "dissoc".to_string(),
+            Type::Function(Function::BuiltIn(|params, _| {
+                if params.len() >= 2 {
+                    let mut entries = get_map(&params[0])?;
+                    let map = Rc::make_mut(&mut entries);
+                    for key in &params[1..] {
+                        map.remove(&key.get_key());
+                    }
+                    Ok(Type::Map(entries))
+                } else {
+                    Err(GradiaError::Function(params.len(), 2, None))
+                }
+            })),
+        ),
+        (
+            "keys".to_string(),
+            Type::Function(Function::BuiltIn(|params, _| {
+                if params.len() == 1 {
+                    Ok(Type::List(
+                        get_map(&params[0])?
+                            .values()
+                            .map(|(key, _)| Expr {
+                                expr: key.clone(),
+                                annotate: None,
+                                span: None,
+                            })
+                            .collect(),
+                    ))
+                } else {
+                    Err(GradiaError::Function(params.len(), 1, None))
+                }
+            })),
+        ),
+        (
+            "values".to_string(),
+            Type::Function(Function::BuiltIn(|params, _| {
+                if params.len() == 1 {
+                    Ok(Type::List(
+                        get_map(&params[0])?
+                            .values()
+                            .map(|(_, value)| Expr {
+                                expr: value.clone(),
+                                annotate: None,
+                                span: None,
+                            })
+                            .collect(),
+                    ))
+                } else {
+                    Err(GradiaError::Function(params.len(), 1, None))
+                }
+            })),
+        ),
+        (
+            "has?".to_string(),
+            Type::Function(Function::BuiltIn(|params, _| {
+                if params.len() == 2 {
+                    Ok(Type::Bool(
+                        get_map(&params[0])?.contains_key(&params[1].get_key()),
+                    ))
+                } else {
+                    Err(GradiaError::Function(params.len(), 2, None))
+                }
+            })),
+        ),
+        (
+            "merge".to_string(),
+            Type::Function(Function::BuiltIn(|params, _| {
+                // Later map takes precedence for the same key
+                if !params.is_empty() {
+                    let mut entries = get_map(&params[0])?;
+                    for other in &params[1..] {
+                        let other = get_map(other)?;
+                        Rc::make_mut(&mut entries).extend(
+                            other
+                                .iter()
+                                .map(|(key, entry)| (key.clone(), entry.clone())),
+                        );
+                    }
+                    Ok(Type::Map(entries))
+                } else {
+                    Err(GradiaError::Function(params.len(), 1, None))
                 }
             })),
         ),
@@ -608,7 +1141,7 @@ pub fn builtin_function() -> Scope {
                     list.reverse();
                     Ok(Type::List(list))
                 } else {
-                    Err(GradiaError::Function(params.len(), 1))
+                    Err(GradiaError::Function(params.len(), 1, None))
                 }
             })),
         ),
@@ -620,7 +1153,7 @@ pub fn builtin_function() -> Scope {
                         params[0].get_list().len() as f64
                     )))
                 } else {
-                    Err(GradiaError::Function(params.len(), 1))
+                    Err(GradiaError::Function(params.len(), 1, None))
                 }
             })),
         ),
@@ -631,10 +1164,10 @@ pub fn builtin_function() -> Scope {
                     Ok(Type::String(
                         params[0]
                             .get_string()
-                            .repeat(params[1].get_number().to_f64() as usize),
+                            .repeat(params[1].get_number()?.to_f64() as usize),
                     ))
                 } else {
-                    Err(GradiaError::Function(params.len(), 2))
+                    Err(GradiaError::Function(params.len(), 2, None))
                 }
             })),
         ),
@@ -651,7 +1184,7 @@ pub fn builtin_function() -> Scope {
                             .join(&params[1].get_string()),
                     ))
                 } else {
-                    Err(GradiaError::Function(params.len(), 2))
+                    Err(GradiaError::Function(params.len(), 2, None))
                 }
             })),
         ),
@@ -666,54 +1199,169 @@ pub fn builtin_function() -> Scope {
                             .map(|i| Expr {
                                 expr: Type::String(i.to_string()),
                                 annotate: None,
+                                span: None,
                             })
                             .collect::<Vec<Expr>>(),
                     ))
                 } else {
-                    Err(GradiaError::Function(params.len(), 2))
+                    Err(GradiaError::Function(params.len(), 2, None))
                 }
             })),
         ),
         (
             "error".to_string(),
             Type::Function(Function::BuiltIn(|params, _| {
-                Err(GradiaError::Runtime(
-                    params
-                        .get(0)
-                        .unwrap_or(&Type::String("Something went wrong".to_string()))
-                        .get_string(),
+                let message = params
+                    .first()
+                    .unwrap_or(&Type::String("Something went wrong".to_string()))
+                    .get_string();
+                // The value in question can be attached like `(error message payload)`
+                match params.get(1) {
+                    Some(payload) => Err(GradiaError::Raised(Rc::new(ErrorValue {
+                        kind: ":runtime".to_string(),
+                        message,
+                        payload: payload.clone(),
+                        span: None,
+                        stack: vec![],
+                    }))),
+                    None => Err(GradiaError::Runtime(message, None)),
+                }
+            })),
+        ),
+        (
+            "raise".to_string(),
+            Type::Function(Function::BuiltIn(|params, _| match params.as_slice() {
+                [Type::Error(error)] => Err(GradiaError::Raised(error.clone())),
+                // Other value is the payload of `:raised` error
+                [value] => Err(GradiaError::Raised(Rc::new(ErrorValue {
+                    kind: ":raised".to_string(),
+                    message: value.get_string(),
+                    payload: value.clone(),
+                    span: None,
+                    stack: vec![],
+                }))),
+                _ => Err(GradiaError::Function(params.len(), 1, None)),
+            })),
+        ),
+        (
+            "make-error".to_string(),
+            Type::Function(Function::BuiltIn(|params, _| {
+                // Error value like `(make-error :kind message payload)`, payload is optional
+                if params.len() == 2 || params.len() == 3 {
+                    let kind = params[0].get_string();
+                    Ok(Type::Error(Rc::new(ErrorValue {
+                        kind: if kind.starts_with(':') {
+                            kind
+                        } else {
+                            format!(":{kind}")
+                        },
+                        message: params[1].get_string(),
+                        payload: params.get(2).cloned().unwrap_or_default(),
+                        span: None,
+                        stack: vec![],
+                    })))
+                } else {
+                    Err(GradiaError::Function(params.len(), 3, None))
+                }
+            })),
+        ),
+        (
+            "error?".to_string(),
+            Type::Function(Function::BuiltIn(|params, _| match params.as_slice() {
+                [value] => Ok(Type::Bool(matches!(value, Type::Error(_)))),
+                _ => Err(GradiaError::Function(params.len(), 1, None)),
+            })),
+        ),
+        (
+            "error-kind".to_string(),
+            Type::Function(Function::BuiltIn(|params, _| {
+                Ok(Type::Symbol(get_error(params)?.kind.clone()))
+            })),
+        ),
+        (
+            "error-message".to_string(),
+            Type::Function(Function::BuiltIn(|params, _| {
+                Ok(Type::String(get_error(params)?.message.clone()))
+            })),
+        ),
+        (
+            "error-payload".to_string(),
+            Type::Function(Function::BuiltIn(|params, _| {
+                Ok(get_error(params)?.payload.clone())
+            })),
+        ),
+        (
+            "error-span".to_string(),
+            Type::Function(Function::BuiltIn(|params, _| {
+                Ok(get_error(params)?.span.map(span_value).unwrap_or_default())
+            })),
+        ),
+        (
+            "error-stack".to_string(),
+            Type::Function(Function::BuiltIn(|params, _| {
+                Ok(Type::List(
+                    get_error(params)?
+                        .stack
+                        .iter()
+                        .map(|i| Expr {
+                            expr: span_value(*i),
+                            annotate: None,
+                            span: None,
+                        })
+                        .collect(),
                 ))
             })),
         ),
         (
             "try".to_string(),
-            Type::Function(Function::BuiltIn(|params, scope| {
-                let tried = if let Type::List(expr) = params[0].clone() {
-                    Expr {
-                        expr: Type::Expr(expr),
-                        annotate: None,
+            Type::Function(Function::Special(|params, scope| {
+                let position = params
+                    .iter()
+                    .position(|i| i.clause("catch").is_some() || i.clause("finally").is_some());
+                // Old form `(try code fallback)` that ignores the error
+                let Some(position) = position else {
+                    if params.len() != 2 {
+                        return Err(GradiaError::Function(params.len(), 2, None));
                     }
-                    .eval(scope)
-                } else {
-                    Ok(params[0].clone())
-                };
-                if params.len() == 2 {
-                    if let Ok(result) = tried {
-                        Ok(result)
-                    } else {
-                        if let Type::List(expr) = params[1].clone() {
-                            Expr {
-                                expr: Type::Expr(expr),
-                                annotate: None,
-                            }
-                            .eval(scope)
-                        } else {
-                            Ok(params[1].clone())
+                    return match params[0].eval(scope) {
+                        Ok(result) => Ok(Flow::Value(result)),
+                        Err(_) => {
+                            expr::take_unwound();
+                            Ok(Flow::Tail(&params[1], scope.clone()))
                         }
+                    };
+                };
+
+                // `(try code... (catch name handler...) (finally cleanup...))`
+                let (code, clauses) = params.split_at(position);
+                let catch = clauses.iter().find(|i| i.clause("catch").is_some());
+                let finally = clauses.iter().find_map(|i| i.clause("finally"));
+                expr::take_unwound();
+                let result = match (block(code, scope), catch) {
+                    (Err(err), Some(catch)) => {
+                        let Some((name, handler)) =
+                            catch.clause("catch").and_then(|i| i.split_first())
+                        else {
+                            return Err(GradiaError::Syntax(
+                                "catch clause should be like `(catch name code...)`".to_string(),
+                                catch.span,
+                            ));
+                        };
+                        let error = err.into_value(expr::take_unwound());
+                        let mut handler_scope = scope.child();
+                        handler_scope.insert(name.expr.get_string(), Type::Error(error));
+                        block(handler, &mut handler_scope)
                     }
-                } else {
-                    Err(GradiaError::Function(params.len(), 2))
+                    (result, _) => result,
+                };
+
+                // Cleanup runs whether the error is raised or not, and its own error takes over
+                if let Some(cleanup) = finally {
+                    let unwound = expr::take_unwound();
+                    block(cleanup, scope)?;
+                    expr::restore_unwound(unwound);
                 }
+                result.map(Flow::Value)
             })),
         ),
         (
@@ -721,9 +1369,9 @@ pub fn builtin_function() -> Scope {
             Type::Function(Function::BuiltIn(|params, _| {
                 exit(
                     params
-                        .get(0)
+                        .first()
                         .unwrap_or(&Type::Number(Fraction::new(0.0)))
-                        .get_number()
+                        .get_number()?
                         .to_f64() as i32,
                 )
             })),
@@ -731,5 +1379,140 @@ pub fn builtin_function() -> Scope {
         ("new-line".to_string(), Type::String("\n".to_string())),
         ("double-quote".to_string(), Type::String("\"".to_string())),
         ("tab".to_string(), Type::String("\t".to_string())),
-    ])
+    ]))
+}
+
+/// Evaluate the value to be bound, annotation of the name is the type of the variable
+fn bind_value(name: &Expr, value: &Expr, scope: &mut Scope) -> Result<Type, GradiaError> {
+    let result = value.eval(scope)?;
+    match &name.annotate {
+        Some(annotate) => annotate
+            .check(result, scope)
+            .map_err(|err| err.at(value.span)),
+        None => Ok(result),
+    }
+}
+
+/// Bind the variable, or the variables of the list pattern
+fn bind_pattern(name: &Expr, value: Type, scope: &Scope) -> Result<(), GradiaError> {
+    if pattern::is_destructuring(name) {
+        pattern::destructure(name, value, scope)
+    } else {
+        scope.insert(name.expr.get_string(), value);
+        Ok(())
+    }
+}
+
+/// Evaluate the code in order and return the value of the last one
+fn block(code: &[Expr], scope: &mut Scope) -> Result<Type, GradiaError> {
+    let mut result = Type::Null;
+    for line in code {
+        result = line.eval(scope)?;
+    }
+    Ok(result)
+}
+
+/// Error value of the only argument, other values are type error
+fn get_error(params: Vec<Type>) -> Result<Rc<ErrorValue>, GradiaError> {
+    match params.as_slice() {
+        [Type::Error(error)] => Ok(error.clone()),
+        [other] => Err(GradiaError::Type(
+            other.clone(),
+            Class::Error.describe(),
+            None,
+        )),
+        _ => Err(GradiaError::Function(params.len(), 1, None)),
+    }
+}
+
+/// Location as the map like `{:line 1 :column 1 :start 0 :end 1}`
+fn span_value(span: Span) -> Type {
+    let mut entries = BTreeMap::new();
+    for (key, value) in [
+        (":line", span.line),
+        (":column", span.column),
+        (":start", span.start),
+        (":end", span.end),
+    ] {
+        let key = Type::Symbol(key.to_string());
+        entries.insert(
+            key.get_key(),
+            (key, Type::Number(Fraction::new(value as f64))),
+        );
+    }
+    Type::Map(Rc::new(entries))
+}
+
+/// Entries of the map, other values are type error
+fn get_map(value: &Type) -> Result<Rc<Entries>, GradiaError> {
+    match value {
+        Type::Map(entries) => Ok(entries.clone()),
+        other => Err(GradiaError::Type(
+            other.clone(),
+            Class::Map.describe(),
+            None,
+        )),
+    }
+}
+
+/// Function made at runtime that owns its data
+fn native(name: String, call: impl Fn(Vec<Type>) -> Result<Type, GradiaError> + 'static) -> Type {
+    Type::Function(Function::Native(Rc::new(Native {
+        name,
+        call: Box::new(call),
+    })))
+}
+
+/// Check every adjacent pair of the values is in the order
+fn compare(params: Vec<Type>, expected: fn(Ordering) -> bool) -> Result<Type, GradiaError> {
+    if params.len() < 2 {
+        return Err(GradiaError::Function(params.len(), 2, None));
+    }
+    for window in params.windows(2) {
+        match window[0].partial_cmp(&window[1]) {
+            Some(order) if expected(order) => {}
+            Some(_) => return Ok(Type::Bool(false)),
+            // NaN isn't in order with any number
+            None if [&window[0], &window[1]]
+                .iter()
+                .all(|i| matches!(i, Type::Number(_) | Type::Float(_))) =>
+            {
+                return Ok(Type::Bool(false))
+            }
+            None => {
+                return Err(GradiaError::Runtime(
+                    format!(
+                        "`{:?}` and `{:?}` can't be compared in order",
+                        window[0], window[1]
+                    ),
+                    None,
+                ))
+            }
+        }
+    }
+    Ok(Type::Bool(true))
+}
+
+/// Fold the numbers from the left, any float among them makes the result float
+fn arithmetic(
+    params: Vec<Type>,
+    exact: fn(Fraction, Fraction) -> Result<Fraction, GradiaError>,
+    inexact: fn(f64, f64) -> f64,
+) -> Result<Type, GradiaError> {
+    let Some((first, rest)) = params.split_first() else {
+        return Err(GradiaError::Function(params.len(), 2, None));
+    };
+    if params.iter().any(|i| matches!(i, Type::Float(_))) {
+        let mut result = first.get_float();
+        for i in rest {
+            result = inexact(result, i.get_float());
+        }
+        Ok(Type::Float(result))
+    } else {
+        let mut result = first.get_number()?;
+        for i in rest {
+            result = exact(result, i.get_number()?)?;
+        }
+        Ok(Type::Number(result))
+    }
 }
@@ -0,0 +1,822 @@
+use crate::expr::{Expr, GradiaError, Span};
+use crate::macros;
+use crate::module::{self, Module};
+use crate::pattern;
+use crate::types::{Class, Scope, Signature, Type};
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
+use std::rc::Rc;
+
+/// What the checker statically knows about a value, `None` classes mean `any`
+#[derive(Clone, Debug)]
+enum Infer {
+    Value(Option<Class>),
+    BuiltIn(Option<Class>),
+    UserDefined(Vec<Option<Class>>, Option<Class>),
+}
+
+impl Infer {
+    fn class(&self) -> Option<Class> {
+        match self {
+            Infer::Value(class) => class.clone(),
+            Infer::BuiltIn(_) | Infer::UserDefined(_, _) => Some(Class::Function),
+        }
+    }
+
+    // Record types of the module are named as they're imported
+    fn rename(&self, names: &HashMap<String, String>) -> Infer {
+        let rename = |class: &Option<Class>| class.as_ref().map(|i| rename(i, names));
+        match self {
+            Infer::Value(class) => Infer::Value(rename(class)),
+            Infer::BuiltIn(class) => Infer::BuiltIn(rename(class)),
+            Infer::UserDefined(params, result) => {
+                Infer::UserDefined(params.iter().map(rename).collect(), rename(result))
+            }
+        }
+    }
+
+    fn join(self, other: Infer) -> Infer {
+        match (self.class(), other.class()) {
+            (Some(Class::Function), Some(Class::Function)) => Infer::Value(Some(Class::Function)),
+            (Some(a), Some(b)) if a.get_type() == b.get_type() => self,
+            (Some(a), Some(b)) if a.is_number() && b.is_number() => {
+                Infer::Value(Some(Class::Number))
+            }
+            (Some(a), Some(b)) => Infer::Value(Some(Class::union(vec![a, b]))),
+            _ => Infer::Value(None),
+        }
+    }
+}
+
+/// Walks parsed expressions and reports every annotation conflict that can be proven before evaluation
+pub struct Checker {
+    scope: HashMap<String, Infer>,
+    errors: Vec<GradiaError>,
+    depth: usize,
+    assigned: HashSet<String>,
+    records: HashSet<String>,
+    // Module whose imports are checked from their source, and the exports of the checked ones
+    module: Option<Rc<Module>>,
+    imported: Rc<RefCell<HashMap<String, Option<Rc<Imported>>>>>,
+    // Errors of the imported modules, which are reported against their own source
+    failed: Vec<GradiaError>,
+    // Whether there's import whose names aren't known, then unknown record types may come from it
+    opaque: bool,
+}
+
+/// What the checker knows about the exports of the module
+struct Imported {
+    values: Vec<(String, Infer)>,
+    records: Vec<String>,
+}
+
+impl Checker {
+    pub fn new() -> Self {
+        Checker {
+            scope: builtin_types(),
+            errors: vec![],
+            depth: 0,
+            assigned: HashSet::new(),
+            records: HashSet::new(),
+            module: None,
+            imported: Rc::default(),
+            failed: vec![],
+            opaque: false,
+        }
+    }
+
+    /// Checker of the program whose imports are loaded by the module loader of the scope
+    pub fn for_program(scope: &Scope) -> Self {
+        Checker {
+            module: scope.module(),
+            ..Checker::new()
+        }
+    }
+
+    pub fn check(&mut self, program: &[Expr]) -> Vec<GradiaError> {
+        for line in program {
+            self.collect(line);
+        }
+
+        // Collect top-level definitions first so that calls before definition and recursion are known
+        let mut definitions: HashMap<String, Infer> = HashMap::new();
+        for line in program {
+            if let Type::Expr(list) = &line.expr {
+                if list
+                    .first()
+                    .is_some_and(|i| self.is_builtin(i, "defrecord"))
+                {
+                    self.record(list);
+                }
+            }
+            if let Some((name, value)) = self.definition(line) {
+                let value = match definitions.get(&name) {
+                    Some(defined) => defined.clone().join(value),
+                    None => value,
+                };
+                self.bind(name.clone(), value.clone());
+                definitions.insert(name, value);
+            }
+        }
+        self.errors.clear();
+
+        for line in program {
+            self.check_annotations(line);
+            self.infer(line);
+        }
+        self.failed.drain(..).chain(self.errors.drain(..)).collect()
+    }
+
+    /// Collect variables updated by `set!` that can hold any value, and names of record types
+    fn collect(&mut self, expr: &Expr) {
+        if let Type::Expr(list) | Type::List(list) = &expr.expr {
+            if let [head, target, ..] = list.as_slice() {
+                match &head.expr {
+                    // Variable bound before its `set!` is seen, like the one of the previous form, can hold any value too
+                    Type::Symbol(symbol) if symbol == "set!" => {
+                        let name = target.expr.get_string();
+                        if let Some(value) = self.scope.get_mut(&name) {
+                            *value = Infer::Value(None);
+                        }
+                        self.assigned.insert(name);
+                    }
+                    Type::Symbol(symbol) if symbol == "defrecord" => {
+                        self.records.insert(target.expr.get_string());
+                    }
+                    _ => {}
+                }
+            }
+            for i in list {
+                self.collect(i);
+            }
+        }
+    }
+
+    /// Report annotations of record types that are never defined, they're likely typo of type name
+    fn check_annotations(&mut self, expr: &Expr) {
+        if let Some(annotate) = &expr.annotate {
+            self.known(annotate, expr.span);
+        }
+        if let Type::Expr(list) | Type::List(list) = &expr.expr {
+            for i in list {
+                self.check_annotations(i);
+            }
+        }
+    }
+
+    fn known(&mut self, class: &Class, span: Option<Span>) {
+        match class {
+            Class::Record(name) if !self.records.contains(name) && !self.opaque => {
+                self.errors.push(GradiaError::Syntax(
+                    format!("unknown type annotation `{name}`"),
+                    span,
+                ));
+            }
+            Class::ListOf(element) => self.known(element, span),
+            Class::Union(classes) => {
+                for class in classes.iter() {
+                    self.known(class, span);
+                }
+            }
+            Class::Signature(signature) => {
+                for class in signature.params.iter().chain([&signature.result]).flatten() {
+                    self.known(class, span);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Bind the constructor, accessors and predicate of the record type
+    fn record(&mut self, list: &[Expr]) {
+        let Some((name, fields)) = list[1..].split_first() else {
+            return;
+        };
+        let name = name.expr.get_string();
+        let class = Some(Class::Record(name.clone()));
+        let classes: Vec<Option<Class>> = fields.iter().map(|i| i.annotate.clone()).collect();
+        self.bind(
+            format!("make-{name}"),
+            Infer::UserDefined(classes.clone(), class.clone()),
+        );
+        for (field, annotate) in fields.iter().zip(classes) {
+            self.bind(
+                format!("{name}-{}", field.expr.get_string()),
+                Infer::UserDefined(vec![class.clone()], annotate),
+            );
+        }
+        self.bind(
+            format!("{name}?"),
+            Infer::UserDefined(vec![None], Some(Class::Bool)),
+        );
+    }
+
+    fn bind(&mut self, name: String, value: Infer) {
+        if self.assigned.contains(&name) {
+            self.scope.insert(name, Infer::Value(None));
+        } else {
+            self.scope.insert(name, value);
+        }
+    }
+
+    fn definition(&mut self, line: &Expr) -> Option<(String, Infer)> {
+        let Type::Expr(list) = &line.expr else {
+            return None;
+        };
+        if !self.is_builtin(list.first()?, "define") {
+            return None;
+        }
+        let target = list.get(1)?;
+        let value = self.infer_define(list);
+        match &target.expr {
+            Type::Expr(header) | Type::List(header) => {
+                Some((header.first()?.expr.get_string(), value))
+            }
+            Type::Symbol(name) => Some((name.to_owned(), value)),
+            _ => None,
+        }
+    }
+
+    fn infer(&mut self, expr: &Expr) -> Infer {
+        let inferred = match &expr.expr {
+            Type::Expr(list) => self.infer_call(list, expr.span),
+            Type::Symbol(name) => self.scope.get(name).cloned().unwrap_or(Infer::Value(None)),
+            other => Infer::Value(class_of(other)),
+        };
+
+        // The annotation is checked at runtime, so afterwards the value has that class
+        if let Some(annotate) = &expr.annotate {
+            self.expect(annotate, &inferred, expr);
+            return match (annotate, inferred.class()) {
+                (Class::Function, Some(Class::Function)) => inferred,
+                _ => annotated(Some(annotate.clone())),
+            };
+        }
+        inferred
+    }
+
+    fn infer_call(&mut self, list: &[Expr], span: Option<Span>) -> Infer {
+        let Some(head) = list.first() else {
+            self.errors.push(GradiaError::Syntax(
+                "first atom in expression should be function, but provided `null` is not function"
+                    .to_string(),
+                span,
+            ));
+            return Infer::Value(None);
+        };
+
+        if self.is_builtin(head, "define") {
+            return self.infer_define(list);
+        } else if self.is_builtin(head, "define-values") {
+            let value = match list.get(1..) {
+                Some([pattern, value]) => self.infer_binding(pattern, value),
+                _ => Infer::Value(None),
+            };
+            if let Some(pattern) = list.get(1) {
+                self.bind_pattern(pattern);
+            }
+            return value;
+        } else if self.is_builtin(head, "defrecord") {
+            self.record(list);
+            return Infer::Value(Some(Class::Null));
+        } else if self.is_builtin(head, "lambda") {
+            return self.infer_lambda(list);
+        } else if self.is_builtin(head, "defmacro") {
+            // Macros are expanded before checking, and their code is data
+            return Infer::Value(Some(Class::Null));
+        } else if self.is_builtin(head, "import") {
+            self.import(&list[1..]);
+            return Infer::Value(Some(Class::Null));
+        } else if self.is_builtin(head, "export") {
+            return Infer::Value(Some(Class::Null));
+        } else if self.is_builtin(head, "quasiquote") {
+            return Infer::Value(None);
+        } else if self.is_builtin(head, "if") {
+            let branches: Vec<Infer> = list[1..].iter().map(|i| self.infer(i)).collect();
+            return match branches.as_slice() {
+                [_, then, otherwise] => then.clone().join(otherwise.clone()),
+                [_, then] => then.clone().join(Infer::Value(Some(Class::Null))),
+                _ => Infer::Value(None),
+            };
+        } else if self.is_builtin(head, "match") {
+            let target = list.get(1).map(|i| self.infer(i));
+            let mut result: Option<Infer> = None;
+            for clause in list.get(2..).unwrap_or_default() {
+                let (Type::Expr(clause) | Type::List(clause)) = &clause.expr else {
+                    continue;
+                };
+                let Some((pattern, code)) = clause.split_first() else {
+                    continue;
+                };
+                let outer = self.scope.clone();
+                self.depth += 1;
+                // Variable pattern without annotation is the value itself
+                match (&pattern.expr, &pattern.annotate, &target) {
+                    (Type::Symbol(name), None, Some(target))
+                        if pattern::variables(pattern).len() == 1 =>
+                    {
+                        self.bind(name.clone(), target.clone())
+                    }
+                    _ => self.bind_pattern(pattern),
+                }
+                let mut branch = Infer::Value(Some(Class::Null));
+                for line in code {
+                    branch = self.infer(line);
+                }
+                self.depth -= 1;
+                self.scope = outer;
+                result = Some(match result {
+                    Some(result) => result.join(branch),
+                    None => branch,
+                });
+            }
+            return result.unwrap_or(Infer::Value(None));
+        } else if self.is_builtin(head, "cond") {
+            let mut result = Infer::Value(Some(Class::Null));
+            for clause in &list[1..] {
+                let branch = match &clause.expr {
+                    Type::Expr(clause) | Type::List(clause) if !clause.is_empty() => {
+                        let values: Vec<Infer> = clause.iter().map(|i| self.infer(i)).collect();
+                        match values.as_slice() {
+                            [_] => Infer::Value(Some(Class::Null)),
+                            [.., last] => last.clone(),
+                            [] => Infer::Value(None),
+                        }
+                    }
+                    _ => Infer::Value(None),
+                };
+                result = result.join(branch);
+            }
+            return result;
+        } else if self.is_builtin(head, "set!") {
+            return match list.get(1..) {
+                Some([target, value]) => self.infer_binding(target, value),
+                _ => Infer::Value(None),
+            };
+        } else if self.is_builtin(head, "let") || self.is_builtin(head, "let*") {
+            let sequential = self.is_builtin(head, "let*");
+            let outer = self.scope.clone();
+            let mut values = vec![];
+            for binding in list.get(1).map(|i| i.expr.get_list()).unwrap_or_default() {
+                if let [name, value] = binding.expr.get_list().as_slice() {
+                    let inferred = self.infer_binding(name, value);
+                    if pattern::is_destructuring(name) {
+                        self.bind_pattern(name);
+                    } else if sequential {
+                        self.bind(name.expr.get_string(), inferred);
+                    } else {
+                        values.push((name.expr.get_string(), inferred));
+                    }
+                }
+            }
+            for (name, value) in values {
+                self.bind(name, value);
+            }
+
+            self.depth += 1;
+            let mut result = Infer::Value(Some(Class::Null));
+            for line in list.get(2..).unwrap_or_default() {
+                result = self.infer(line);
+            }
+            self.depth -= 1;
+            self.scope = outer;
+            return result;
+        } else if self.is_builtin(head, "try") {
+            let (code, clauses): (Vec<&Expr>, Vec<&Expr>) = list[1..]
+                .iter()
+                .partition(|i| i.clause("catch").is_none() && i.clause("finally").is_none());
+            let mut branches: Vec<Infer> = code.iter().map(|i| self.infer(i)).collect();
+            if clauses.is_empty() {
+                return match branches.as_slice() {
+                    [tried, fallback] => tried.clone().join(fallback.clone()),
+                    _ => Infer::Value(None),
+                };
+            }
+
+            // `(catch name handler...)` binds the error value, and the value of `finally` is ignored
+            let mut result = branches.pop().unwrap_or(Infer::Value(Some(Class::Null)));
+            for expr in clauses {
+                if let Some([name, handler @ ..]) = expr.clause("catch") {
+                    let outer = self.scope.clone();
+                    self.depth += 1;
+                    self.bind(name.expr.get_string(), Infer::Value(Some(Class::Error)));
+                    let mut caught = Infer::Value(Some(Class::Null));
+                    for line in handler {
+                        caught = self.infer(line);
+                    }
+                    self.depth -= 1;
+                    self.scope = outer;
+                    result = result.join(caught);
+                } else if let Some(cleanup) = expr.clause("finally") {
+                    for line in cleanup {
+                        self.infer(line);
+                    }
+                }
+            }
+            return result;
+        } else if self.is_builtin(head, "cast") {
+            for i in &list[1..] {
+                self.infer(i);
+            }
+            if let [_, _, Expr {
+                expr: Type::String(class),
+                ..
+            }] = list
+            {
+                match Class::from(class.to_owned()) {
+                    Ok(class) => return Infer::Value(class),
+                    Err(err) => self.errors.push(err.at(list[2].span)),
+                }
+            }
+            return Infer::Value(None);
+        }
+
+        let function = self.infer(head);
+        let args: Vec<Infer> = list[1..].iter().map(|i| self.infer(i)).collect();
+        match function {
+            Infer::BuiltIn(result) => Infer::Value(result),
+            Infer::UserDefined(params, result) => {
+                if params.len() != args.len() {
+                    self.errors
+                        .push(GradiaError::Function(args.len(), params.len(), span));
+                } else {
+                    for ((param, arg), expr) in params.iter().zip(args).zip(&list[1..]) {
+                        if let Some(param) = param {
+                            self.expect(param, &arg, expr);
+                        }
+                    }
+                }
+                annotated(result)
+            }
+            Infer::Value(Some(class)) if !matches!(class, Class::Function) => {
+                self.errors.push(GradiaError::Syntax(format!(
+                    "first atom in expression should be function, but provided `{:?}` is not function",
+                    head.expr
+                ), head.span));
+                Infer::Value(None)
+            }
+            Infer::Value(_) => Infer::Value(None),
+        }
+    }
+
+    fn infer_define(&mut self, list: &[Expr]) -> Infer {
+        let (name, value) = match list.get(1).map(|i| &i.expr) {
+            Some(Type::Expr(header) | Type::List(header)) if !header.is_empty() => (
+                header[0].expr.get_string(),
+                self.infer_function(&header[1..], list[1].annotate.clone(), &list[2..]),
+            ),
+            _ => {
+                let target = list.get(1).cloned().unwrap_or_default();
+                let value = match list.get(2) {
+                    Some(value) => self.infer_binding(&target, value),
+                    None => annotated(target.annotate.clone()),
+                };
+                (target.expr.get_string(), value)
+            }
+        };
+
+        // Top-level definitions are already collected before checking
+        if self.depth > 0 {
+            self.bind(name, value.clone());
+        }
+        value
+    }
+
+    fn infer_lambda(&mut self, list: &[Expr]) -> Infer {
+        match list.get(1) {
+            Some(params) => {
+                self.infer_function(&params.expr.get_list(), params.annotate.clone(), &list[2..])
+            }
+            None => Infer::Value(Some(Class::Function)),
+        }
+    }
+
+    fn infer_function(&mut self, params: &[Expr], result: Option<Class>, body: &[Expr]) -> Infer {
+        self.depth += 1;
+        let outer = self.scope.clone();
+        // Parameters after `&optional`, `&rest` and `&key` make the arity unknown
+        let mut section = "";
+        for param in params {
+            match &param.expr {
+                Type::Symbol(marker) if marker.starts_with('&') => section = marker,
+                Type::Expr(pair) | Type::List(pair)
+                    if pair.len() == 2 && matches!(section, "&optional" | "&key") =>
+                {
+                    self.bind_pattern(&pair[0])
+                }
+                _ => self.bind_pattern(param),
+            }
+        }
+        let variadic = !section.is_empty();
+
+        let mut inferred = Infer::Value(Some(Class::Null));
+        for line in body {
+            inferred = self.infer(line);
+        }
+        self.scope = outer;
+        self.depth -= 1;
+
+        // Declared return type is checked against the returning code
+        if let (Some(result), Some(last)) = (&result, body.last()) {
+            self.expect(result, &inferred, last);
+        }
+        if variadic {
+            return Infer::Value(Some(Class::Function));
+        }
+        Infer::UserDefined(
+            params.iter().map(|i| i.annotate.clone()).collect(),
+            result.or(inferred.class()),
+        )
+    }
+
+    /// Bind the exports of the module imported by `(import "path" options...)`
+    fn import(&mut self, params: &[Expr]) {
+        let imported = match params.split_first() {
+            Some((
+                Expr {
+                    expr: Type::String(path),
+                    ..
+                },
+                options,
+            )) => self.exports(path).zip(module::Options::parse(options).ok()),
+            _ => None,
+        };
+        let Some((imported, options)) = imported else {
+            self.opaque = true;
+            return;
+        };
+
+        let names: HashMap<String, String> = imported
+            .records
+            .iter()
+            .filter_map(|name| Some((name.clone(), options.name(name)?)))
+            .collect();
+        for (name, value) in imported.values.iter() {
+            if let Some(name) = options.name(name) {
+                self.bind(name, value.rename(&names));
+            }
+        }
+        self.records.extend(names.into_values());
+    }
+
+    // Check the module on its own checker, the exports are known after the module is expanded and checked
+    fn exports(&mut self, path: &str) -> Option<Rc<Imported>> {
+        let current = self.module.clone()?;
+        let path = current.resolve(path);
+        if let Some(imported) = self.imported.borrow().get(&path) {
+            return imported.clone();
+        }
+        // Module in the import cycle isn't known until it's checked
+        self.imported.borrow_mut().insert(path.clone(), None);
+
+        let (forms, mut scope) = current.read(&path).ok()?;
+        let forms = forms
+            .iter()
+            .map(|i| macros::expand(i, &mut scope))
+            .collect::<Result<Vec<Expr>, GradiaError>>()
+            .ok()?;
+        let mut checker = Checker {
+            module: scope.module(),
+            imported: self.imported.clone(),
+            ..Checker::new()
+        };
+        let errors = checker.check(&forms);
+        self.failed.extend(errors);
+
+        let exported = module::exported(&forms);
+        let imported = Rc::new(Imported {
+            values: exported
+                .iter()
+                .filter_map(|name| Some((name.clone(), checker.scope.get(name)?.clone())))
+                .collect(),
+            records: exported
+                .into_iter()
+                .filter(|name| checker.records.contains(name))
+                .collect(),
+        });
+        self.imported
+            .borrow_mut()
+            .insert(path, Some(imported.clone()));
+        Some(imported)
+    }
+
+    /// Bind the variables of the parameter or the pattern with their annotations
+    fn bind_pattern(&mut self, pattern: &Expr) {
+        for variable in pattern::variables(pattern) {
+            self.bind(
+                variable.expr.get_string(),
+                annotated(variable.annotate.clone()),
+            );
+        }
+    }
+
+    /// Infer the value to be bound, annotation of the name is the type of the variable
+    fn infer_binding(&mut self, name: &Expr, value: &Expr) -> Infer {
+        let inferred = self.infer(value);
+        match &name.annotate {
+            Some(annotate) => {
+                self.expect(annotate, &inferred, value);
+                annotated(Some(annotate.clone()))
+            }
+            None => inferred,
+        }
+    }
+
+    /// Report when the value is provably not the expected class
+    fn expect(&mut self, expected: &Class, value: &Infer, expr: &Expr) {
+        // Elements of list literal are checked one by one
+        if let (Class::ListOf(element), Type::List(list)) = (expected, &expr.expr) {
+            self.expect_elements(element, list);
+            return;
+        }
+        if let Some(class) = value.class() {
+            if !expected.overlaps(&class) {
+                self.errors.push(GradiaError::Type(
+                    expr.expr.clone(),
+                    expected.describe(),
+                    expr.span,
+                ));
+            }
+        }
+    }
+
+    /// Check elements of list literal, its nested lists are quoted too and aren't calls
+    fn expect_elements(&mut self, element: &Class, list: &[Expr]) {
+        for i in list {
+            match (element, &i.expr) {
+                (Class::ListOf(nested), Type::List(list) | Type::Expr(list)) => {
+                    self.expect_elements(nested, list)
+                }
+                (_, Type::Expr(list)) => {
+                    self.expect(element, &Infer::Value(Some(list_class(list))), i)
+                }
+                (_, value) => self.expect(element, &Infer::Value(class_of(value)), i),
+            }
+        }
+    }
+
+    fn is_builtin(&self, head: &Expr, name: &str) -> bool {
+        matches!(&head.expr, Type::Symbol(symbol) if symbol == name)
+            && matches!(self.scope.get(name), Some(Infer::BuiltIn(_)))
+    }
+}
+
+impl Default for Checker {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Check a whole program and return all provable type errors
+pub fn check(program: &[Expr]) -> Vec<GradiaError> {
+    Checker::new().check(program)
+}
+
+// Record types in the class are named with the new names
+fn rename(class: &Class, names: &HashMap<String, String>) -> Class {
+    let rename_option = |class: &Option<Class>| class.as_ref().map(|i| rename(i, names));
+    match class {
+        Class::Record(name) => Class::Record(names.get(name).unwrap_or(name).clone()),
+        Class::ListOf(element) => Class::ListOf(Rc::new(rename(element, names))),
+        Class::Union(classes) => {
+            Class::Union(Rc::new(classes.iter().map(|i| rename(i, names)).collect()))
+        }
+        Class::Signature(signature) => Class::Signature(Rc::new(Signature {
+            params: signature.params.iter().map(rename_option).collect(),
+            result: rename_option(&signature.result),
+        })),
+        other => other.clone(),
+    }
+}
+
+/// What is known about the value of the class, function signature tells how it's called
+fn annotated(class: Option<Class>) -> Infer {
+    match class {
+        Some(Class::Signature(signature)) => {
+            Infer::UserDefined(signature.params.clone(), signature.result.clone())
+        }
+        other => Infer::Value(other),
+    }
+}
+
+fn class_of(value: &Type) -> Option<Class> {
+    Some(match value {
+        Type::Function(_) => Class::Function,
+        Type::List(list) => list_class(list),
+        Type::Symbol(_) => Class::Symbol,
+        Type::Number(_) => Class::Exact,
+        Type::Float(_) => Class::Float,
+        Type::String(_) => Class::String,
+        Type::Bool(_) => Class::Bool,
+        Type::Null => Class::Null,
+        Type::Record(record) => Class::Record(record.kind.name.clone()),
+        Type::Map(_) => Class::Map,
+        Type::Error(_) => Class::Error,
+        Type::Expr(_) => return None,
+    })
+}
+
+/// Literal list knows its element type when all elements agree, its nested lists are quoted too
+fn list_class(list: &[Expr]) -> Class {
+    let element = list
+        .iter()
+        .map(|i| {
+            Infer::Value(match &i.expr {
+                Type::Expr(nested) => Some(list_class(nested)),
+                other => class_of(other),
+            })
+        })
+        .reduce(Infer::join)
+        .and_then(|i| i.class());
+    match element {
+        Some(element) => Class::ListOf(Rc::new(element)),
+        None => Class::List,
+    }
+}
+
+fn builtin_types() -> HashMap<String, Infer> {
+    let mut scope = HashMap::new();
+    let mut register = |names: &[&str], class: Option<Class>| {
+        for name in names {
+            scope.insert(name.to_string(), Infer::BuiltIn(class.clone()));
+        }
+    };
+    register(&["+", "-", "*", "/", "%", "^", "len"], Some(Class::Number));
+    register(&["inexact->exact"], Some(Class::Exact));
+    register(&["hash-map", "assoc", "dissoc", "merge"], Some(Class::Map));
+    register(&["has?", "error?"], Some(Class::Bool));
+    register(&["make-error"], Some(Class::Error));
+    register(&["keys", "values"], Some(Class::List));
+    register(&["exact->inexact"], Some(Class::Float));
+    register(
+        &["concat", "input", "type", "repeat", "join", "error-message"],
+        Some(Class::String),
+    );
+    register(
+        &[
+            "=", "!=", "equal?", "eq?", ">", ">=", "<", "<=", "&", "|", "!", "and", "or",
+        ],
+        Some(Class::Bool),
+    );
+    register(
+        &[
+            "cdr",
+            "range",
+            "map",
+            "filter",
+            "reverse",
+            "split",
+            "error-stack",
+        ],
+        Some(Class::List),
+    );
+    register(
+        &[
+            "print",
+            "debug",
+            "for",
+            "defrecord",
+            "defmacro",
+            "import",
+            "export",
+        ],
+        Some(Class::Null),
+    );
+    register(&["gensym", "error-kind"], Some(Class::Symbol));
+    register(&["lambda"], Some(Class::Function));
+    register(
+        &[
+            "cast",
+            "get",
+            "eval",
+            "define",
+            "define-values",
+            "set!",
+            "let",
+            "let*",
+            "if",
+            "cond",
+            "match",
+            "car",
+            "reduce",
+            "error",
+            "raise",
+            "error-payload",
+            "error-span",
+            "try",
+            "exit",
+            "quasiquote",
+            "unquote",
+            "unquote-splicing",
+            "macroexpand",
+        ],
+        None,
+    );
+    for (name, value) in [("new-line", "\n"), ("double-quote", "\""), ("tab", "\t")] {
+        scope.insert(
+            name.to_string(),
+            Infer::Value(class_of(&Type::String(value.to_string()))),
+        );
+    }
+    scope
+}
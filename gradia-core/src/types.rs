@@ -1,62 +1,311 @@
-use crate::expr::{Expr, GradiaError};
+use crate::expr::{Expr, GradiaError, Span};
 use crate::fraction::Fraction;
-use std::collections::HashMap;
+use crate::module::Module;
+use crate::pattern;
+use std::cell::RefCell;
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, HashMap};
 use std::fmt::{self, Debug};
+use std::iter::repeat;
+use std::rc::Rc;
 
-pub type Scope = HashMap<String, Type>;
+/// Chain of variable frames, clones share the same frames
+#[derive(Clone, Default)]
+pub struct Scope(Rc<Frame>);
 
-#[derive(Clone)]
+#[derive(Default)]
+struct Frame {
+    variables: RefCell<HashMap<String, Type>>,
+    // Record types defined by `defrecord`, that annotations refer to by the name
+    records: RefCell<HashMap<String, Rc<RecordType>>>,
+    parent: Option<Scope>,
+    // File that the code of this frame belongs to, it's only on the top frame of each file
+    module: Option<Rc<Module>>,
+}
+
+#[derive(Clone, Default)]
 pub enum Type {
     Function(Function),
     Expr(Vec<Expr>),
     List(Vec<Expr>),
     Symbol(String),
     Number(Fraction),
+    Float(f64),
     String(String),
     Bool(bool),
+    Record(Rc<Record>),
+    Map(Rc<Entries>),
+    Error(Rc<ErrorValue>),
+    #[default]
     Null,
 }
 
+/// Keys and values of map by the printed key, so that it's kept in stable order
+pub type Entries = BTreeMap<String, (Type, Type)>;
+
 #[derive(Clone, Debug)]
 pub enum Function {
     BuiltIn(fn(Vec<Type>, &mut Scope) -> Result<Type, GradiaError>),
-    UserDefined(Vec<Expr>, Vec<Type>),
+    Special(for<'a> fn(&'a [Expr], &mut Scope) -> Result<Flow<'a>, GradiaError>),
+    Native(Rc<Native>),
+    UserDefined(Rc<Lambda>),
+    // Function that takes the code of arguments and returns the code to be evaluated instead
+    Macro(Rc<Lambda>),
+}
+
+/// Built-in function that is made at runtime with its own data
+pub struct Native {
+    pub name: String,
+    pub call: Box<dyn Fn(Vec<Type>) -> Result<Type, GradiaError>>,
+}
+
+/// Result of special form, `Tail` is the code to be evaluated in place of the form
+pub enum Flow<'a> {
+    Value(Type),
+    Tail(&'a Expr, Scope),
 }
 
-#[derive(Copy, Clone, Debug)]
+/// Parameters and code of user-defined function with the scope where it was created
+#[derive(Debug)]
+pub struct Lambda {
+    pub params: Vec<Expr>,
+    pub result: Option<Class>,
+    pub body: Vec<Expr>,
+    pub scope: Scope,
+}
+
+#[derive(Clone, Debug)]
 pub enum Class {
     Function,
+    Signature(Rc<Signature>),
     List,
+    ListOf(Rc<Class>),
+    Union(Rc<Vec<Class>>),
+    Record(String),
+    Map,
+    Error,
     Symbol,
     Number,
+    Exact,
+    Float,
     String,
     Bool,
     Null,
 }
 
+/// Name and typed fields of record type defined by `defrecord`
+#[derive(Debug)]
+pub struct RecordType {
+    pub name: String,
+    pub fields: Vec<(String, Option<Class>)>,
+}
+
+/// Value of user-defined record type, the values are in the order of the fields
+#[derive(Debug)]
+pub struct Record {
+    pub kind: Rc<RecordType>,
+    pub values: Vec<Type>,
+}
+
+/// Error as the value, that `catch` binds and `raise` throws
+#[derive(Clone, Debug)]
+pub struct ErrorValue {
+    /// Keyword like `:type`, errors of the interpreter have `:runtime`, `:function`, `:type` or `:syntax`
+    pub kind: String,
+    pub message: String,
+    pub payload: Type,
+    pub span: Option<Span>,
+    /// Locations of the calls that the error went out of, the innermost first
+    pub stack: Vec<Span>,
+}
+
+/// Parameter and return types of function, `None` means `any`
+#[derive(Debug)]
+pub struct Signature {
+    pub params: Vec<Option<Class>>,
+    pub result: Option<Class>,
+}
+
+impl Scope {
+    /// Create new frame whose parent is this scope
+    pub fn child(&self) -> Scope {
+        Scope(Rc::new(Frame {
+            variables: RefCell::new(HashMap::new()),
+            records: RefCell::new(HashMap::new()),
+            parent: Some(self.clone()),
+            module: None,
+        }))
+    }
+
+    /// Create new frame for the top level of the file
+    pub fn with_module(&self, module: Rc<Module>) -> Scope {
+        Scope(Rc::new(Frame {
+            variables: RefCell::new(HashMap::new()),
+            records: RefCell::new(HashMap::new()),
+            parent: Some(self.clone()),
+            module: Some(module),
+        }))
+    }
+
+    /// File that the code in this scope belongs to
+    pub fn module(&self) -> Option<Rc<Module>> {
+        let mut frame = self;
+        loop {
+            if let Some(module) = &frame.0.module {
+                return Some(module.clone());
+            }
+            frame = frame.0.parent.as_ref()?;
+        }
+    }
+
+    /// Look up the variable from the nearest frame that has it
+    pub fn get(&self, name: &str) -> Option<Type> {
+        let mut frame = self;
+        loop {
+            if let Some(value) = frame.0.variables.borrow().get(name) {
+                return Some(value.clone());
+            }
+            frame = frame.0.parent.as_ref()?;
+        }
+    }
+
+    /// Bind the variable in the current frame
+    pub fn insert(&self, name: String, value: Type) {
+        self.0.variables.borrow_mut().insert(name, value);
+    }
+
+    /// Record type of the name that is visible from this scope
+    pub fn record(&self, name: &str) -> Option<Rc<RecordType>> {
+        let mut frame = self;
+        loop {
+            if let Some(kind) = frame.0.records.borrow().get(name) {
+                return Some(kind.clone());
+            }
+            frame = frame.0.parent.as_ref()?;
+        }
+    }
+
+    /// Define the record type in the current frame, so that annotations can refer to it by the name
+    pub fn insert_record(&self, name: String, kind: Rc<RecordType>) {
+        self.0.records.borrow_mut().insert(name, kind);
+    }
+
+    /// Update the nearest existing binding, returns false if there's no such variable
+    pub fn set(&self, name: &str, value: Type) -> bool {
+        let mut frame = self;
+        loop {
+            if let Some(variable) = frame.0.variables.borrow_mut().get_mut(name) {
+                *variable = value;
+                return true;
+            }
+            match &frame.0.parent {
+                Some(parent) => frame = parent,
+                None => return false,
+            }
+        }
+    }
+}
+
+/// Function defined in the frame of a call refers back to the frame, so the frame and the function
+/// keep each other alive. The frame is cleared when the only references left are from such functions
+impl Drop for Scope {
+    fn drop(&mut self) {
+        let frame = &self.0;
+        // Top level of the file lives as long as the program
+        if frame.module.is_some() || frame.parent.is_none() {
+            return;
+        }
+        let count = Rc::strong_count(frame);
+        if count == 1 {
+            return;
+        }
+        let Ok(variables) = frame.variables.try_borrow() else {
+            return;
+        };
+        let cyclic = variables
+            .values()
+            .filter(|value| match value {
+                Type::Function(Function::UserDefined(lambda) | Function::Macro(lambda)) => {
+                    Rc::strong_count(lambda) == 1 && Rc::ptr_eq(&lambda.scope.0, frame)
+                }
+                _ => false,
+            })
+            .count();
+        drop(variables);
+        if cyclic + 1 == count {
+            let variables = std::mem::take(&mut *frame.variables.borrow_mut());
+            drop(variables);
+        }
+    }
+}
+
+impl From<HashMap<String, Type>> for Scope {
+    fn from(variables: HashMap<String, Type>) -> Self {
+        Scope(Rc::new(Frame {
+            variables: RefCell::new(variables),
+            records: RefCell::new(HashMap::new()),
+            parent: None,
+            module: None,
+        }))
+    }
+}
+
+impl Debug for Native {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Native")
+            .field("name", &self.name)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Debug for Scope {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Scope").finish_non_exhaustive()
+    }
+}
+
 impl Type {
-    pub fn get_number(&self) -> Fraction {
-        match &self {
-            Type::Number(n) => n.to_owned(),
-            Type::String(s) | Type::Symbol(s) => Fraction::from(s.to_string())
-                .unwrap_or(Fraction::new(s.trim().parse().unwrap_or(0.0))),
+    /// Exact value of the number, NaN and infinity are error as they have no exact value
+    pub fn get_number(&self) -> Result<Fraction, GradiaError> {
+        let float = match &self {
+            Type::Number(n) => return Ok(n.to_owned()),
+            Type::Float(f) => *f,
+            Type::String(s) | Type::Symbol(s) => match Fraction::from(s.to_string()) {
+                Some(n) => return Ok(n),
+                None => s.trim().parse().unwrap_or(0.0),
+            },
             Type::Bool(b) => {
                 if *b {
-                    Fraction::new(1.0)
+                    1.0
                 } else {
-                    Fraction::new(0.0)
+                    0.0
                 }
             }
             Type::Expr(x) | Type::List(x) => {
-                x.get(0).cloned().unwrap_or_default().expr.get_number()
+                return x.first().cloned().unwrap_or_default().expr.get_number()
+            }
+            Type::Function(_) | Type::Record(_) | Type::Map(_) | Type::Error(_) | Type::Null => 0.0,
+        };
+        Fraction::try_new(float).ok_or_else(|| {
+            GradiaError::Runtime(format!("`{float:?}` has no exact representation"), None)
+        })
+    }
+
+    /// Inexact value of the number, exact numbers are rounded to the nearest float
+    pub fn get_float(&self) -> f64 {
+        match &self {
+            Type::Float(f) => *f,
+            Type::String(s) | Type::Symbol(s) if Fraction::from(s.to_string()).is_none() => {
+                s.trim().parse().unwrap_or(0.0)
             }
-            Type::Function(_) | Type::Null => Fraction::new(0.0),
+            other => other.get_number().map_or(f64::NAN, |i| i.to_f64()),
         }
     }
 
     pub fn get_string(&self) -> String {
         match &self {
             Type::Number(n) => n.display(),
+            Type::Float(f) => format!("{f:?}"),
             Type::String(s) => s.to_owned(),
             Type::Bool(b) => b.to_string(),
             Type::Symbol(v) => v.to_owned(),
@@ -67,9 +316,12 @@ impl Type {
     pub fn get_bool(&self) -> bool {
         match &self {
             Type::Number(n) => *n != Fraction::new(0.0),
+            Type::Float(f) => *f != 0.0,
             Type::String(s) | Type::Symbol(s) => !s.is_empty(),
             Type::Expr(s) | Type::List(s) => !s.is_empty(),
             Type::Bool(b) => *b,
+            Type::Record(_) | Type::Error(_) => true,
+            Type::Map(m) => !m.is_empty(),
             Type::Function(_) | Type::Null => false,
         }
     }
@@ -77,6 +329,7 @@ impl Type {
     pub fn get_type(&self) -> String {
         match &self {
             Type::Number(_) => "number",
+            Type::Float(_) => "float",
             Type::String(_) => "string",
             Type::Bool(_) => "bool",
             Type::Expr(_) => "expr",
@@ -84,84 +337,644 @@ impl Type {
             Type::List(_) => "list",
             Type::Null => "null",
             Type::Function(_) => "function",
+            Type::Map(_) => "map",
+            Type::Error(_) => "error",
+            Type::Record(record) => return record.kind.name.clone(),
         }
         .to_string()
     }
 
+    /// Identity of the value as key of map, equal values print the same
+    pub fn get_key(&self) -> String {
+        format!("{self:?}")
+    }
+
+    /// Check both are the same value, values shared by reference are compared by the reference
+    pub fn is_identical(&self, other: &Type) -> bool {
+        match (self, other) {
+            (Type::Function(a), Type::Function(b)) => a == b,
+            (Type::Record(a), Type::Record(b)) => Rc::ptr_eq(a, b),
+            (Type::Map(a), Type::Map(b)) => Rc::ptr_eq(a, b),
+            // Lists are copied when they're passed, so only empty lists are the same
+            (Type::List(a), Type::List(b)) | (Type::Expr(a), Type::Expr(b)) => {
+                a.is_empty() && b.is_empty()
+            }
+            (a, b) => a == b,
+        }
+    }
+
     pub fn get_list(&self) -> Vec<Expr> {
         match &self {
             Type::Expr(e) => e.to_owned(),
             Type::List(l) => l.to_owned(),
+            // Entries of map are pairs of the key and the value
+            Type::Map(m) => m
+                .values()
+                .map(|(key, value)| Expr {
+                    expr: Type::List(vec![
+                        Expr {
+                            expr: key.clone(),
+                            annotate: None,
+                            span: None,
+                        },
+                        Expr {
+                            expr: value.clone(),
+                            annotate: None,
+                            span: None,
+                        },
+                    ]),
+                    annotate: None,
+                    span: None,
+                })
+                .collect(),
             other => vec![Expr {
                 expr: other.to_owned().to_owned(),
                 annotate: None,
+                span: None,
             }],
         }
     }
 }
 
+impl Lambda {
+    /// Bind the arguments on new frame of the scope where the function was created.
+    /// Parameter can be list pattern like `(a b)` that destructures the argument.
+    /// Parameters after `&optional` and `&key` can have default like `(name default)`,
+    /// and the one after `&rest` is the list of the rest arguments
+    pub fn bind(&self, args: Vec<Type>, sources: &[Expr]) -> Result<Scope, GradiaError> {
+        let mut scope = self.scope.child();
+        let required = self.params.iter().take_while(|i| !is_marker(i)).count();
+
+        let mut args: Vec<(Type, Option<Span>)> = args
+            .into_iter()
+            .zip(sources.iter().map(|i| i.span).chain(repeat(None)))
+            .collect();
+        let given = args.len();
+
+        // Only positional parameters, that is the most of functions
+        if required == self.params.len() {
+            if given != required {
+                return Err(GradiaError::Function(given, required, None));
+            }
+            for (param, (value, span)) in self.params.iter().zip(args) {
+                bind_param(param, value, span, &scope)?;
+            }
+            return Ok(scope);
+        }
+
+        // Keyword arguments like `:name value` follow the required and optional ones,
+        // so keyword given as value of positional parameter isn't taken
+        let mut keywords = HashMap::new();
+        if self.params.iter().any(|i| i.expr.get_string() == "&key") {
+            let mut section = String::new();
+            let optional = self
+                .params
+                .iter()
+                .filter(|i| {
+                    if is_marker(i) {
+                        section = i.expr.get_string();
+                    }
+                    !is_marker(i) && section == "&optional"
+                })
+                .count();
+            let mut rest = args.split_off((required + optional).min(given)).into_iter();
+            let mut positional = args;
+            while let Some((value, span)) = rest.next() {
+                match value {
+                    Type::Symbol(name) if name.starts_with(':') => {
+                        let Some(arg) = rest.next() else {
+                            return Err(GradiaError::Runtime(
+                                format!("keyword argument `{name}` needs its value"),
+                                span,
+                            ));
+                        };
+                        keywords.insert(name[1..].to_string(), (arg, span));
+                    }
+                    value => positional.push((value, span)),
+                }
+            }
+            args = positional;
+        }
+
+        let mut maximum = required;
+        let mut section = String::new();
+        let mut positional = args.into_iter();
+        for param in &self.params {
+            if is_marker(param) {
+                section = param.expr.get_string();
+                continue;
+            }
+            let (name, default) = match &param.expr {
+                Type::Expr(pair) | Type::List(pair)
+                    if pair.len() == 2 && matches!(section.as_str(), "&optional" | "&key") =>
+                {
+                    (&pair[0], Some(&pair[1]))
+                }
+                _ => (param, None),
+            };
+            let (value, span) = match section.as_str() {
+                "" => positional
+                    .next()
+                    .ok_or(GradiaError::Function(given, required, None))?,
+                "&optional" => {
+                    maximum += 1;
+                    match positional.next() {
+                        Some(arg) => arg,
+                        None => (default_value(default, &mut scope)?, None),
+                    }
+                }
+                "&rest" => {
+                    maximum = usize::MAX;
+                    let rest = positional.by_ref().map(|(value, span)| Expr {
+                        expr: value,
+                        annotate: None,
+                        span,
+                    });
+                    (Type::List(rest.collect()), None)
+                }
+                "&key" => match keywords.remove(&name.expr.get_string()) {
+                    Some(((value, span), _)) => (value, span),
+                    None => (default_value(default, &mut scope)?, None),
+                },
+                other => {
+                    return Err(GradiaError::Syntax(
+                        format!("unknown parameter marker `{other}`, it should be `&optional`, `&rest` or `&key`"),
+                        None,
+                    ))
+                }
+            };
+            bind_param(name, value, span, &scope)?;
+        }
+
+        if positional.next().is_some() {
+            return Err(GradiaError::Function(given, maximum, None));
+        }
+        if let Some((name, (_, span))) = keywords.into_iter().next() {
+            return Err(GradiaError::Runtime(
+                format!("there's no keyword parameter `{name}`"),
+                span,
+            ));
+        }
+        Ok(scope)
+    }
+
+    /// Narrow the function to the signature, returns None if they conflict.
+    /// Arguments of the signature are the required and optional parameters, and the extra ones go to `&rest`
+    fn conform(&self, signature: &Signature) -> Option<Lambda> {
+        let mut expected = signature.params.iter();
+        let mut section = String::new();
+        let mut variadic = false;
+        let mut params = vec![];
+        for param in &self.params {
+            if is_marker(param) {
+                section = param.expr.get_string();
+                variadic |= section == "&rest";
+                params.push(param.clone());
+                continue;
+            }
+            let param = match section.as_str() {
+                "" => narrow(param, expected.next()?)?,
+                "&optional" => match (expected.next(), &param.expr) {
+                    (Some(class), Type::Expr(pair) | Type::List(pair)) if pair.len() == 2 => {
+                        let name = narrow(&pair[0], class)?;
+                        let mut param = param.clone();
+                        if let Type::Expr(pair) | Type::List(pair) = &mut param.expr {
+                            pair[0] = name;
+                        }
+                        param
+                    }
+                    (Some(class), _) => narrow(param, class)?,
+                    // Optional parameter that the signature doesn't give keeps its default
+                    (None, _) => param.clone(),
+                },
+                // Rest and keyword parameters aren't given by position of the signature
+                _ => param.clone(),
+            };
+            params.push(param);
+        }
+        // Extra arguments of the signature are taken only by `&rest`
+        if expected.next().is_some() && !variadic {
+            return None;
+        }
+        if self.result.is_some() && !accepts(&signature.result, &self.result) {
+            return None;
+        }
+        Some(Lambda {
+            params,
+            result: self.result.clone().or(signature.result.clone()),
+            body: self.body.clone(),
+            scope: self.scope.clone(),
+        })
+    }
+}
+
+impl ErrorValue {
+    /// Name of the kind in the message like `Type` of `Type Error!`
+    pub fn title(&self) -> String {
+        let name = self.kind.trim_start_matches(':');
+        let mut chars = name.chars();
+        match chars.next() {
+            Some(first) => first.to_uppercase().chain(chars).collect(),
+            None => String::new(),
+        }
+    }
+}
+
 impl Class {
     pub fn from(source: String) -> Result<Option<Class>, GradiaError> {
+        // Function signature like `(number number)->number`
+        if let Some((params, result)) = split_signature(&source) {
+            let mut classes = vec![];
+            for param in params {
+                classes.push(Class::from(param.to_string())?);
+            }
+            return Ok(Some(Class::Signature(Rc::new(Signature {
+                params: classes,
+                result: Class::from(result.to_string())?,
+            }))));
+        }
+
+        // Union of alternatives like `number|string`, it's any if one of them is any
+        let alternatives = split_union(&source);
+        if alternatives.len() > 1 {
+            let mut classes = vec![];
+            for alternative in alternatives {
+                match Class::from(alternative.to_string())? {
+                    Some(class) => classes.push(class),
+                    None => return Ok(None),
+                }
+            }
+            return Ok(Some(Class::union(classes)));
+        }
+
+        // Optional type like `string?` is union with null
+        if let Some(class) = source.strip_suffix('?') {
+            return Ok(
+                Class::from(class.to_string())?.map(|class| Class::union(vec![class, Class::Null]))
+            );
+        }
+
+        // List of the element type like `list<number>`
+        if let Some(element) = source
+            .strip_prefix("list<")
+            .and_then(|i| i.strip_suffix('>'))
+        {
+            return Ok(Class::from(element.to_string())?
+                .map(|element| Class::ListOf(Rc::new(element)))
+                .or(Some(Class::List)));
+        }
+
         Ok(match source.as_str() {
             "function" => Some(Class::Function),
             "list" => Some(Class::List),
+            "map" => Some(Class::Map),
+            "error" => Some(Class::Error),
             "symbol" => Some(Class::Symbol),
             "number" => Some(Class::Number),
+            "exact" => Some(Class::Exact),
+            "float" => Some(Class::Float),
             "string" => Some(Class::String),
             "bool" => Some(Class::Bool),
             "null" => Some(Class::Null),
             "any" => None,
+            // Other name is the record type defined by `defrecord`, or imported like `prefix/name`
+            other
+                if other.starts_with(|c: char| c.is_alphabetic())
+                    && other
+                        .chars()
+                        .all(|c| c.is_alphanumeric() || matches!(c, '-' | '_' | '/')) =>
+            {
+                Some(Class::Record(other.to_string()))
+            }
             other => {
-                return Err(GradiaError::Syntax(format!(
-                    "unknown type annotation `{other}`"
-                )))
+                return Err(GradiaError::Syntax(
+                    format!("unknown type annotation `{other}`"),
+                    None,
+                ))
             }
         })
     }
 
-    pub fn parse(&self, value: Type) -> Type {
-        match self {
+    /// Convert the value to this class, record types are resolved on the scope
+    pub fn parse(&self, value: Type, scope: &Scope) -> Result<Type, GradiaError> {
+        Ok(match self {
             Class::Symbol => Type::Symbol(value.get_string()),
             Class::Bool => Type::Bool(value.get_bool()),
-            Class::Number => Type::Number(value.get_number()),
+            Class::Number => match value {
+                Type::Float(_) => value,
+                other => Type::Number(other.get_number()?),
+            },
+            Class::Exact => Type::Number(value.get_number()?),
+            Class::Float => Type::Float(value.get_float()),
             Class::String => Type::String(value.get_string()),
             Class::List => Type::List(value.get_list()),
+            Class::ListOf(element) => {
+                let mut list = vec![];
+                for i in value.get_list() {
+                    list.push(Expr {
+                        expr: element.parse(i.expr.clone(), scope)?,
+                        ..i
+                    });
+                }
+                Type::List(list)
+            }
             Class::Null => Type::Null,
-            Class::Function => Type::Function(Function::BuiltIn(|params, _| {
-                if let Some(val) = params.get(0) {
-                    Ok(val.clone())
-                } else {
-                    Err(GradiaError::Function(params.len(), 1))
+            // List of pairs becomes map
+            Class::Map => match value {
+                Type::Map(_) => value,
+                other => {
+                    let mut entries = BTreeMap::new();
+                    for pair in other.get_list() {
+                        let pair = pair.expr.get_list();
+                        let key = pair.first().cloned().unwrap_or_default().expr;
+                        let value = pair.get(1).cloned().unwrap_or_default().expr;
+                        entries.insert(key.get_key(), (key, value));
+                    }
+                    Type::Map(Rc::new(entries))
                 }
-            })),
-        }
+            },
+            // Functions, records and errors can't be made from other values
+            Class::Function | Class::Signature(_) | Class::Record(_) | Class::Error => {
+                return self.check(value, scope)
+            }
+            // Value that already has one of the alternatives is kept, otherwise it becomes the first one
+            Class::Union(classes) => match self.check(value.clone(), scope) {
+                Ok(value) => value,
+                Err(_) => return classes[0].parse(value, scope),
+            },
+        })
     }
 
     pub fn get_type(&self) -> String {
-        format!("{self:?}").to_lowercase()
+        match self {
+            Class::Signature(signature) => format!(
+                "({})->{}",
+                signature
+                    .params
+                    .iter()
+                    .map(type_name)
+                    .collect::<Vec<String>>()
+                    .join(" "),
+                type_name(&signature.result)
+            ),
+            Class::ListOf(element) => format!("list<{}>", element.get_type()),
+            Class::Record(name) => name.to_owned(),
+            Class::Union(classes) => match classes.as_slice() {
+                [class, Class::Null] => format!("{}?", class.get_type()),
+                classes => classes
+                    .iter()
+                    .map(|i| i.get_type())
+                    .collect::<Vec<String>>()
+                    .join("|"),
+            },
+            other => format!("{other:?}").to_lowercase(),
+        }
+    }
+
+    /// Check the value has this class, user-defined function gets the signature to be checked on its calls.
+    /// Record type is the one that the name refers to on the scope, it's syntax error if there's no such type
+    pub fn check(&self, value: Type, scope: &Scope) -> Result<Type, GradiaError> {
+        let valid = match (self, &value) {
+            // The first alternative that the value has is used
+            (Class::Union(classes), _) => {
+                for class in classes.iter() {
+                    match class.check(value.clone(), scope) {
+                        Ok(value) => return Ok(value),
+                        Err(err @ GradiaError::Syntax(_, _)) => return Err(err),
+                        Err(_) => {}
+                    }
+                }
+                false
+            }
+            (Class::Record(name), _) => {
+                let Some(kind) = scope.record(name) else {
+                    return Err(GradiaError::Syntax(
+                        format!("unknown type annotation `{name}`"),
+                        None,
+                    ));
+                };
+                matches!(&value, Type::Record(record) if Rc::ptr_eq(&record.kind, &kind))
+            }
+            (Class::Signature(signature), Type::Function(Function::UserDefined(lambda))) => {
+                if let Some(lambda) = lambda.conform(signature) {
+                    return Ok(Type::Function(Function::UserDefined(Rc::new(lambda))));
+                }
+                false
+            }
+            // Built-in functions don't declare their types
+            (Class::Signature(_), Type::Function(_)) => true,
+            // Nested list of quoted list is the code that isn't evaluated, it's checked as list
+            (Class::List, Type::Expr(list)) => return Ok(Type::List(list.clone())),
+            (Class::ListOf(element), Type::List(list) | Type::Expr(list)) => {
+                let mut checked = vec![];
+                for i in list {
+                    checked.push(Expr {
+                        expr: element
+                            .check(i.expr.clone(), scope)
+                            .map_err(|err| err.at(i.span))?,
+                        ..i.clone()
+                    });
+                }
+                return Ok(Type::List(checked));
+            }
+            (Class::Number, Type::Float(_)) | (Class::Exact, Type::Number(_)) => true,
+            _ => self.get_type() == value.get_type(),
+        };
+        if valid {
+            Ok(value)
+        } else {
+            Err(GradiaError::Type(value, self.describe(), None))
+        }
+    }
+
+    /// Name of the class for messages, union lists its alternatives
+    pub fn describe(&self) -> String {
+        match self {
+            Class::Union(classes) => {
+                let mut names: Vec<String> = classes
+                    .iter()
+                    .map(|i| format!("`{}`", i.get_type()))
+                    .collect();
+                let last = names.pop().unwrap_or_default();
+                format!("types {} or {last}", names.join(", "))
+            }
+            other => format!("type `{}`", other.get_type()),
+        }
+    }
+
+    /// Union of the classes, nested unions and duplicates are flattened
+    pub fn union(classes: Vec<Class>) -> Class {
+        let mut flat: Vec<Class> = vec![];
+        for class in classes {
+            let alternatives = match class {
+                Class::Union(classes) => classes.to_vec(),
+                other => vec![other],
+            };
+            for class in alternatives {
+                if !flat.iter().any(|i| i.get_type() == class.get_type()) {
+                    flat.push(class);
+                }
+            }
+        }
+        if flat.len() == 1 {
+            flat.remove(0)
+        } else {
+            Class::Union(Rc::new(flat))
+        }
+    }
+
+    pub fn is_number(&self) -> bool {
+        matches!(self, Class::Number | Class::Exact | Class::Float)
+    }
+
+    /// Check every value of the other class also has this class
+    pub fn accepts(&self, other: &Class) -> bool {
+        match (self, other) {
+            (this, Class::Union(others)) => others.iter().all(|other| this.accepts(other)),
+            (Class::Union(classes), other) => classes.iter().any(|this| this.accepts(other)),
+            (Class::Number, other) => other.is_number(),
+            (Class::Function, Class::Signature(_)) | (Class::List, Class::ListOf(_)) => true,
+            (Class::ListOf(this), Class::ListOf(other)) => this.accepts(other),
+            (Class::Signature(this), Class::Signature(other)) => {
+                this.params.len() == other.params.len()
+                    && this
+                        .params
+                        .iter()
+                        .zip(&other.params)
+                        .all(|(this, other)| accepts(other, this))
+                    && accepts(&this.result, &other.result)
+            }
+            _ => self.get_type() == other.get_type(),
+        }
+    }
+
+    /// Check some value can have both classes
+    pub fn overlaps(&self, other: &Class) -> bool {
+        match (self, other) {
+            (Class::Union(classes), other) | (other, Class::Union(classes)) => {
+                classes.iter().any(|class| class.overlaps(other))
+            }
+            // Empty list has every element type
+            (Class::Signature(_), Class::Signature(_)) | (Class::ListOf(_), Class::ListOf(_)) => {
+                true
+            }
+            _ => self.accepts(other) || other.accepts(self),
+        }
+    }
+}
+
+/// Check every value of the other class also has this class, `None` means `any`
+pub fn accepts(this: &Option<Class>, other: &Option<Class>) -> bool {
+    match (this, other) {
+        (None, _) => true,
+        (Some(_), None) => false,
+        (Some(this), Some(other)) => this.accepts(other),
+    }
+}
+
+fn type_name(class: &Option<Class>) -> String {
+    match class {
+        Some(class) => class.get_type(),
+        None => "any".to_string(),
+    }
+}
+
+/// Split union like `number|list<string>` into the alternatives
+fn split_union(source: &str) -> Vec<&str> {
+    let mut alternatives = vec![];
+    let mut depth = 0;
+    let mut start = 0;
+    let mut previous = ' ';
+    for (index, c) in source.char_indices() {
+        match c {
+            '(' | '<' => depth += 1,
+            // `>` of the arrow in signature isn't closing
+            '>' if previous == '-' => {}
+            ')' | '>' => depth -= 1,
+            '|' if depth == 0 => {
+                alternatives.push(&source[start..index]);
+                start = index + 1;
+            }
+            _ => {}
+        }
+        previous = c;
+    }
+    alternatives.push(&source[start..]);
+    alternatives
+}
+
+/// Split signature like `(number number)->number` into the parameter types and the return type
+fn split_signature(source: &str) -> Option<(Vec<&str>, &str)> {
+    let inner = source.strip_prefix('(')?;
+    let mut params = vec![];
+    let mut depth = 0;
+    let mut start = 0;
+    for (index, c) in inner.char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' if depth > 0 => depth -= 1,
+            ')' => {
+                params.push(&inner[start..index]);
+                let params = params.into_iter().filter(|i| !i.is_empty()).collect();
+                return Some((params, inner[index + 1..].strip_prefix("->")?));
+            }
+            ' ' | '\n' | '\t' if depth == 0 => {
+                params.push(&inner[start..index]);
+                start = index + c.len_utf8();
+            }
+            _ => {}
+        }
     }
+    None
 }
 
 impl Debug for Type {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         let fmt = match &self {
-            Type::String(s) => format!("\"{s}\""),
+            Type::String(s) => escape(s),
             Type::Number(n) => n.display(),
+            Type::Float(f) => format!("{f:?}"),
             Type::Bool(b) => b.to_string(),
-            Type::Function(Function::UserDefined(args, code)) => {
+            Type::Function(Function::UserDefined(lambda) | Function::Macro(lambda)) => {
                 format!(
-                    "(lambda '({}) {})",
-                    args.iter()
+                    "({} ({}){} {})",
+                    match self {
+                        Type::Function(Function::Macro(_)) => "macro",
+                        _ => "lambda",
+                    },
+                    lambda
+                        .params
+                        .iter()
                         .map(|i| format!("{i:?}"))
                         .collect::<Vec<String>>()
                         .join(" "),
-                    code.iter()
+                    match &lambda.result {
+                        Some(result) => format!(":{}", result.get_type()),
+                        None => String::new(),
+                    },
+                    lambda
+                        .body
+                        .iter()
                         .map(|i| format!("{i:?}"))
                         .collect::<Vec<String>>()
                         .join(" ")
                 )
             }
             Type::Function(Function::BuiltIn(n)) => format!("function({n:?})"),
+            Type::Function(Function::Special(n)) => format!("special-form({n:?})"),
+            Type::Function(Function::Native(native)) => format!("function({})", native.name),
+            Type::Record(record) => format!(
+                "#{}{{{}}}",
+                record.kind.name,
+                record
+                    .kind
+                    .fields
+                    .iter()
+                    .zip(&record.values)
+                    .map(|((field, _), value)| format!("{field}: {value:?}"))
+                    .collect::<Vec<String>>()
+                    .join(", ")
+            ),
             Type::Symbol(v) => v.to_owned(),
             Type::List(l) => format!(
                 "'({})",
@@ -177,14 +990,151 @@ impl Debug for Type {
                     .collect::<Vec<String>>()
                     .join(" ")
             ),
+            Type::Error(error) => format!(
+                "#error{{kind: {}, message: {:?}, payload: {:?}}}",
+                error.kind, error.message, error.payload
+            ),
+            Type::Map(m) => format!(
+                "{{{}}}",
+                m.values()
+                    .map(|(key, value)| format!("{key:?} {value:?}"))
+                    .collect::<Vec<String>>()
+                    .join(" ")
+            ),
             Type::Null => "null".to_string(),
         };
         write!(f, "{fmt}")
     }
 }
 
-impl Default for Type {
-    fn default() -> Self {
-        Type::Null
+impl PartialEq for Function {
+    fn eq(&self, other: &Function) -> bool {
+        match (self, other) {
+            (Function::BuiltIn(a), Function::BuiltIn(b)) => std::ptr::fn_addr_eq(*a, *b),
+            (Function::Special(a), Function::Special(b)) => std::ptr::fn_addr_eq(*a, *b),
+            (Function::Native(a), Function::Native(b)) => Rc::ptr_eq(a, b),
+            (Function::UserDefined(a), Function::UserDefined(b))
+            | (Function::Macro(a), Function::Macro(b)) => Rc::ptr_eq(a, b),
+            _ => false,
+        }
+    }
+}
+
+/// Structural equality, exact and inexact numbers are different values
+impl PartialEq for Type {
+    fn eq(&self, other: &Type) -> bool {
+        match (self, other) {
+            (Type::Function(a), Type::Function(b)) => a == b,
+            (Type::Expr(a), Type::Expr(b)) | (Type::List(a), Type::List(b)) => a == b,
+            (Type::Symbol(a), Type::Symbol(b)) | (Type::String(a), Type::String(b)) => a == b,
+            (Type::Number(a), Type::Number(b)) => a == b,
+            (Type::Float(a), Type::Float(b)) => a == b,
+            (Type::Bool(a), Type::Bool(b)) => a == b,
+            (Type::Record(a), Type::Record(b)) => {
+                Rc::ptr_eq(&a.kind, &b.kind) && a.values == b.values
+            }
+            (Type::Map(a), Type::Map(b)) => a == b,
+            (Type::Error(a), Type::Error(b)) => Rc::ptr_eq(a, b),
+            (Type::Null, Type::Null) => true,
+            _ => false,
+        }
+    }
+}
+
+/// Numbers are ordered by the value across exactness, strings, symbols and lists are in lexicographic order
+impl PartialOrd for Type {
+    fn partial_cmp(&self, other: &Type) -> Option<Ordering> {
+        match (self, other) {
+            (Type::Number(a), Type::Number(b)) => a.partial_cmp(b),
+            (Type::Float(a), Type::Float(b)) => a.partial_cmp(b),
+            (Type::Number(a), Type::Float(b)) => compare_float(a, *b),
+            (Type::Float(a), Type::Number(b)) => compare_float(b, *a).map(Ordering::reverse),
+            (Type::Symbol(a), Type::Symbol(b)) | (Type::String(a), Type::String(b)) => {
+                a.partial_cmp(b)
+            }
+            (Type::Bool(a), Type::Bool(b)) => a.partial_cmp(b),
+            (Type::Expr(a), Type::Expr(b)) | (Type::List(a), Type::List(b)) => {
+                for (a, b) in a.iter().zip(b) {
+                    match a.expr.partial_cmp(&b.expr)? {
+                        Ordering::Equal => {}
+                        order => return Some(order),
+                    }
+                }
+                a.len().partial_cmp(&b.len())
+            }
+            (a, b) if a == b => Some(Ordering::Equal),
+            _ => None,
+        }
+    }
+}
+
+/// Check the argument with the annotation of the parameter, and bind it or destructure it by the list pattern
+fn bind_param(
+    param: &Expr,
+    value: Type,
+    span: Option<Span>,
+    scope: &Scope,
+) -> Result<(), GradiaError> {
+    let value = match &param.annotate {
+        Some(annotate) => annotate.check(value, scope).map_err(|err| err.at(span))?,
+        None => value,
+    };
+    if pattern::is_destructuring(param) {
+        pattern::destructure(param, value, scope).map_err(|err| err.at(span))
+    } else {
+        scope.insert(param.expr.get_string(), value);
+        Ok(())
+    }
+}
+
+// Marker like `&optional` that begins the section of parameters
+fn is_marker(param: &Expr) -> bool {
+    matches!(&param.expr, Type::Symbol(name) if name.starts_with('&'))
+}
+
+// Parameter narrowed to the class of the signature, None if the parameter doesn't accept the class
+fn narrow(param: &Expr, expected: &Option<Class>) -> Option<Expr> {
+    if !accepts(&param.annotate, expected) {
+        return None;
+    }
+    Some(Expr {
+        annotate: expected.clone().or(param.annotate.clone()),
+        ..param.clone()
+    })
+}
+
+/// Value of the parameter that isn't passed, the default is evaluated when it's called
+fn default_value(default: Option<&Expr>, scope: &mut Scope) -> Result<Type, GradiaError> {
+    match default {
+        Some(default) => default.eval(scope),
+        None => Ok(Type::Null),
+    }
+}
+
+/// Write the string as literal that is read back to the same string
+fn escape(source: &str) -> String {
+    let mut result = String::from('"');
+    for c in source.chars() {
+        match c {
+            '"' => result.push_str("\\\""),
+            '\\' => result.push_str("\\\\"),
+            '\n' => result.push_str("\\n"),
+            '\t' => result.push_str("\\t"),
+            '\r' => result.push_str("\\r"),
+            c if c.is_control() => result.push_str(&format!("\\u{{{:x}}}", c as u32)),
+            c => result.push(c),
+        }
+    }
+    result.push('"');
+    result
+}
+
+/// Compare exact number with the exact value of the float
+fn compare_float(exact: &Fraction, float: f64) -> Option<Ordering> {
+    match Fraction::from_float(float) {
+        Some(float) => exact.partial_cmp(&float),
+        None if float.is_nan() => None,
+        None if float > 0.0 => Some(Ordering::Less),
+        None => Some(Ordering::Greater),
     }
 }
@@ -0,0 +1,47 @@
+use gradia_core::{
+    expr::GradiaError,
+    parser::{read, tokenize_with_trivia},
+};
+
+mod common;
+
+use common::display;
+
+#[test]
+fn comments_are_skipped() {
+    assert_eq!(display("(+ 1 ; one\n 2) ; three"), "3");
+    assert_eq!(display("(+ 1 #| two |# 2)"), "3");
+    assert_eq!(display("(+ 1 #| outer #| inner |# |# 2)"), "3");
+    assert_eq!(display("(+ 1 #;(* 10 10) 2)"), "3");
+    assert_eq!(display("(+ 1 #;x:number 2)"), "3");
+    assert_eq!(read("; only comment").unwrap().len(), 0);
+}
+
+#[test]
+fn semicolon_ends_symbol() {
+    assert_eq!(display("(define x 1) x;comment"), "1");
+}
+
+#[test]
+fn comments_are_kept_as_trivia() {
+    let code = "; head\n(+ 1 #| block |# 2) #;(skipped)";
+    let (tokens, trivia) = tokenize_with_trivia(code.to_string()).unwrap();
+    assert_eq!(tokens.len(), 1);
+    let comments: Vec<&str> = trivia.iter().map(|i| i.source.as_str()).collect();
+    assert_eq!(comments, ["; head", "#| block |#", "#;(skipped)"]);
+    for comment in &trivia {
+        assert_eq!(&code[comment.span.start..comment.span.end], comment.source);
+    }
+}
+
+#[test]
+fn unclosed_comment_is_syntax_error() {
+    assert!(matches!(
+        read("#| open"),
+        Err(GradiaError::Syntax(_, Some(_)))
+    ));
+    assert!(matches!(
+        read("(+ 1 #;)"),
+        Err(GradiaError::Syntax(_, Some(_)))
+    ));
+}
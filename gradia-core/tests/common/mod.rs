@@ -0,0 +1,21 @@
+//! Helpers shared by the integration tests
+#![allow(dead_code)]
+
+use gradia_core::{
+    expr::GradiaError, macros::expand, parser::read, std::builtin_function, types::Type,
+};
+
+/// Expand and evaluate the forms one by one like the program runs, and return the last value
+pub fn run(code: &str) -> Result<Type, GradiaError> {
+    let mut scope = builtin_function();
+    let mut result = Type::Null;
+    for expr in read(code)? {
+        result = expand(&expr, &mut scope)?.eval(&mut scope)?;
+    }
+    Ok(result)
+}
+
+/// Printed value of the code that runs without error
+pub fn display(code: &str) -> String {
+    format!("{:?}", run(code).unwrap())
+}
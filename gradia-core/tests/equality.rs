@@ -0,0 +1,75 @@
+use gradia_core::expr::GradiaError;
+
+mod common;
+
+use common::{display, run};
+
+const FLOATS: &str = "(define inf (/ (exact->inexact 1) 0))
+                      (define nan (- inf inf))";
+
+#[test]
+fn numbers_are_equal_regardless_of_exactness() {
+    assert_eq!(display("(= 1 (exact->inexact 1))"), "true");
+    assert_eq!(display("(= 1/2 (exact->inexact 1/2))"), "true");
+    // 1/3 has no exact float
+    assert_eq!(display("(= 1/3 (exact->inexact 1/3))"), "false");
+    assert_eq!(display("(= 1 \"1\")"), "false");
+    assert_eq!(display("(= :a 'a)"), "false");
+}
+
+#[test]
+fn equal_distinguishes_exactness() {
+    assert_eq!(display("(equal? 1 (exact->inexact 1))"), "false");
+    assert_eq!(display("(equal? '(1 (2)) '(1 (2)))"), "true");
+    assert_eq!(display("(equal? {:a 1} {:a 1})"), "true");
+}
+
+#[test]
+fn eq_compares_shared_values_by_reference() {
+    // Lists are copied when they're passed, so the same list isn't identical
+    assert_eq!(display("(define p '(1 2)) (equal? p p)"), "true");
+    assert_eq!(display("(define p '(1 2)) (eq? p p)"), "false");
+    assert_eq!(display("(eq? '() '())"), "true");
+
+    let code = "(defrecord pt x) (define a (make-pt 1))";
+    assert_eq!(display(&format!("{code} (eq? a a)")), "true");
+    assert_eq!(display(&format!("{code} (eq? a (make-pt 1))")), "false");
+    assert_eq!(display(&format!("{code} (= a (make-pt 1))")), "true");
+
+    assert_eq!(display("(define m {:a 1}) (eq? m m)"), "true");
+    assert_eq!(display("(eq? {:a 1} {:a 1})"), "false");
+    assert_eq!(display("(eq? 1 1)"), "true");
+}
+
+#[test]
+fn ordering_of_different_types_is_error() {
+    assert_eq!(display("(< 1 (exact->inexact 2))"), "true");
+    assert_eq!(display("(< \"a\" \"b\")"), "true");
+    for code in ["(< 1 \"a\")", "(> :a 1)", "(<= '(1) 1)"] {
+        assert!(
+            matches!(run(code), Err(GradiaError::Runtime(_, Some(_)))),
+            "{code}"
+        );
+    }
+}
+
+#[test]
+fn infinity_is_beyond_every_exact_number() {
+    let big = "99999999999999999999999999";
+    assert_eq!(display(&format!("{FLOATS} (< {big} inf)")), "true");
+    assert_eq!(display(&format!("{FLOATS} (> (- {big}) (- inf))")), "true");
+    assert_eq!(display(&format!("{FLOATS} (= inf inf)")), "true");
+    assert_eq!(display(&format!("{FLOATS} (= {big} inf)")), "false");
+}
+
+#[test]
+fn nan_is_not_ordered() {
+    for op in ["=", "<", ">", "<=", ">="] {
+        assert_eq!(display(&format!("{FLOATS} ({op} 1 nan)")), "false", "{op}");
+        assert_eq!(
+            display(&format!("{FLOATS} ({op} nan nan)")),
+            "false",
+            "{op}"
+        );
+    }
+}
@@ -0,0 +1,71 @@
+mod common;
+
+use common::display;
+
+// Lines of the calls in the stack of the caught error `e`
+const LINES: &str = "(map (error-stack e) (lambda (i) (get i :line)))";
+
+#[test]
+fn stack_has_calls_of_functions() {
+    let code = format!(
+        "(define (inner) (raise \"boom\"))
+         (define (outer) (+ 1 (inner)))
+         (define e (try (outer) (catch e e)))
+         {LINES}"
+    );
+    assert_eq!(display(&code), "'(2 3)");
+}
+
+#[test]
+fn stack_has_tail_calls() {
+    let code = format!(
+        "(define (countdown n) (if (= n 0) (raise \"done\") (countdown (- n 1))))
+         (define e (try (countdown 3) (catch e e)))
+         {LINES}"
+    );
+    assert_eq!(display(&code), "'(1 2)");
+}
+
+#[test]
+fn built_in_error_has_no_frame_of_its_own() {
+    let code = format!("(define e (try (car 1 2) (catch e e))) {LINES}");
+    assert_eq!(display(&code), "'()");
+}
+
+#[test]
+fn error_span_is_where_it_is_raised() {
+    let code = "(define (f x)
+                  (missing x))
+                (define e (try (f 1) (catch e e)))
+                (get (error-span e) :line)";
+    assert_eq!(display(code), "2");
+    assert_eq!(
+        display("(error-span (make-error :oops \"message\"))"),
+        "null"
+    );
+}
+
+#[test]
+fn finally_runs_and_error_is_raised_again() {
+    let code = "(define log null)
+                (define (f) (raise \"boom\"))
+                (define e
+                  (try
+                    (try (f) (finally (set! log 'cleanup)))
+                    (catch e e)))
+                `(,log ,(error-message e) ,(len (error-stack e)))";
+    assert_eq!(display(code), "'(cleanup \"boom\" 1)");
+
+    // Error raised again from the handler keeps its stack
+    let code = format!(
+        "(define (f) (raise \"boom\"))
+         (define e (try (try (f) (catch e (raise e))) (catch e e)))
+         {LINES}"
+    );
+    assert_eq!(display(&code), "'(2)");
+
+    // Error of the cleanup code takes over
+    let code = "(define e (try (try (raise \"first\") (finally (raise \"second\"))) (catch e e)))
+                (error-message e)";
+    assert_eq!(display(code), "\"second\"");
+}
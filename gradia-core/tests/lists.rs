@@ -0,0 +1,46 @@
+use gradia_core::expr::GradiaError;
+
+mod common;
+
+use common::{display, run};
+
+#[test]
+fn list_of_element_type_is_checked() {
+    assert_eq!(display("(define x:list<number> '(1 2)) x"), "'(1 2)");
+    assert_eq!(display("(define x:list<string> '()) x"), "'()");
+    assert!(matches!(
+        run("(define x:list<string> 1)"),
+        Err(GradiaError::Type(_, _, _))
+    ));
+}
+
+#[test]
+fn nested_list_of_element_type_is_checked() {
+    let code = "(define x:list<list<string>> '((\"a\") (\"b\" \"c\"))) x";
+    assert_eq!(display(code), "'('(\"a\") '(\"b\" \"c\"))");
+    assert!(matches!(
+        run("(define x:list<list<string>> '((\"a\") (\"b\" 1)))"),
+        Err(GradiaError::Type(_, _, _))
+    ));
+    assert!(matches!(
+        run("(define x:list<list<string>> '((\"a\") \"b\"))"),
+        Err(GradiaError::Type(_, _, _))
+    ));
+}
+
+#[test]
+fn wrong_element_is_reported_at_its_location() {
+    let code = "(define (f xs:list<number>) xs)\n(f '(1 2 \"three\"))";
+    let err = run(code).unwrap_err();
+    let GradiaError::Type(value, expected, Some(span)) = &err else {
+        panic!("{err:?}");
+    };
+    assert_eq!(format!("{value:?}"), "\"three\"");
+    assert_eq!(expected, "type `number`");
+    assert_eq!((span.line, span.column), (2, 10));
+
+    let code = "(define x:list<list<number>> '((1) (2 \"b\")))";
+    let err = run(code).unwrap_err();
+    let report = err.report("main", code);
+    assert!(report.starts_with("main:1:39:"), "{report}");
+}
@@ -0,0 +1,24 @@
+mod common;
+
+use common::display;
+
+const TWICE: &str = "(defmacro twice (x) `(* 2 ,x))";
+
+#[test]
+fn macro_is_expanded_before_evaluation() {
+    assert_eq!(display(&format!("{TWICE} (twice 21)")), "42");
+    assert_eq!(display(&format!("{TWICE} (let ((x 2)) (twice x))")), "4");
+}
+
+#[test]
+fn variable_hides_macro_of_same_name() {
+    assert_eq!(display(&format!("{TWICE} (let ((twice 1)) twice)")), "1");
+    assert_eq!(display(&format!("{TWICE} (let* ((twice 1)) twice)")), "1");
+    assert_eq!(display(&format!("{TWICE} ((lambda (twice) twice) 5)")), "5");
+    let code = format!("{TWICE} ((lambda (twice) (twice 5)) (lambda (x) (+ x 1)))");
+    assert_eq!(display(&code), "6");
+    let code = format!("{TWICE} (define (apply twice) (twice 5)) (apply (lambda (x) (- x 1)))");
+    assert_eq!(display(&code), "4");
+    let code = format!("{TWICE} (match `(,(lambda (x) x) 3) ((twice y) (twice y)))");
+    assert_eq!(display(&code), "3");
+}
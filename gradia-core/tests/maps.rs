@@ -0,0 +1,81 @@
+use gradia_core::expr::GradiaError;
+
+mod common;
+
+use common::{display, run};
+
+#[test]
+fn map_literal_has_pairs() {
+    assert_eq!(display("{}"), "{}");
+    assert_eq!(display("{:a 1 :b (+ 1 1)}"), "{:a 1 :b 2}");
+    assert_eq!(display("{:a 1 :a 2}"), "{:a 2}");
+    assert!(matches!(
+        run("{:a 1 :b}"),
+        Err(GradiaError::Function(3, _, Some(_)))
+    ));
+}
+
+#[test]
+fn map_prints_in_stable_order() {
+    assert_eq!(display("{:b 2 :a 1}"), display("{:a 1 :b 2}"));
+    assert_eq!(
+        display("{1 :one \"1\" :str 1/2 :half}"),
+        display("{1/2 :half 1 :one \"1\" :str}")
+    );
+    assert_eq!(display("(keys {:c 3 :a 1 :b 2})"), "'(:a :b :c)");
+    assert_eq!(display("(values {:c 3 :a 1 :b 2})"), "'(1 2 3)");
+}
+
+#[test]
+fn get_returns_value_or_default() {
+    assert_eq!(display("(get {:a 1} :a)"), "1");
+    assert_eq!(display("(get {:a 1} :z)"), "null");
+    assert_eq!(display("(get {:a 1} :z 0)"), "0");
+    assert_eq!(display("(get {\"k\" '(1 2)} \"k\")"), "'(1 2)");
+    assert!(matches!(run("(get 1 :a)"), Err(GradiaError::Type(_, _, _))));
+}
+
+#[test]
+fn assoc_and_dissoc_return_new_map() {
+    assert_eq!(display("(assoc {:a 1} :b 2)"), "{:a 1 :b 2}");
+    assert_eq!(display("(assoc {:a 1} :a 3)"), "{:a 3}");
+    assert_eq!(display("(dissoc {:a 1 :b 2} :a)"), "{:b 2}");
+    assert_eq!(display("(dissoc {:a 1} :z)"), "{:a 1}");
+    let code = "(define m {:a 1}) (assoc m :b 2) (dissoc m :a) m";
+    assert_eq!(display(code), "{:a 1}");
+}
+
+#[test]
+fn has_checks_key() {
+    assert_eq!(display("(has? {:a 1} :a)"), "true");
+    assert_eq!(display("(has? {:a null} :a)"), "true");
+    assert_eq!(display("(has? {:a 1} :b)"), "false");
+    assert_eq!(display("(has? {1 :one} \"1\")"), "false");
+}
+
+#[test]
+fn merge_prefers_later_map() {
+    assert_eq!(
+        display("(merge {:a 1 :b 2} {:b 3 :c 4})"),
+        "{:a 1 :b 3 :c 4}"
+    );
+    assert_eq!(display("(merge {:a 1} {})"), "{:a 1}");
+}
+
+#[test]
+fn map_annotation_rejects_other_values() {
+    assert_eq!(display("(define m:map {:a 1}) m"), "{:a 1}");
+    for value in ["1", "\"s\"", "'(1 2)", "null"] {
+        assert!(
+            matches!(
+                run(&format!("(define m:map {value})")),
+                Err(GradiaError::Type(_, _, Some(_)))
+            ),
+            "{value}"
+        );
+    }
+    assert!(matches!(
+        run("(define (f m:map) m) (f \"s\")"),
+        Err(GradiaError::Type(_, _, Some(_)))
+    ));
+}
@@ -0,0 +1,170 @@
+use gradia_core::{
+    expr::GradiaError,
+    fraction::Fraction,
+    macros::expand,
+    module::{program, Loader},
+    parser::read,
+    std::builtin_function,
+    typecheck::Checker,
+    types::{Scope, Type},
+};
+use std::collections::HashMap;
+
+/// Modules given in place of files
+struct Files(HashMap<String, String>);
+
+impl Loader for Files {
+    fn load(&self, path: &str) -> Result<String, String> {
+        self.0
+            .get(path)
+            .cloned()
+            .ok_or_else(|| "there's no such module".to_string())
+    }
+}
+
+fn scope(files: &[(&str, &str)]) -> Scope {
+    let files = files
+        .iter()
+        .map(|(path, code)| (path.to_string(), code.to_string()))
+        .collect();
+    program(builtin_function(), Files(files), None)
+}
+
+fn run(files: &[(&str, &str)], code: &str) -> Result<Type, GradiaError> {
+    let mut scope = scope(files);
+    let mut result = Type::Null;
+    for expr in read(code)? {
+        result = expand(&expr, &mut scope)?.eval(&mut scope)?;
+    }
+    Ok(result)
+}
+
+fn number(n: f64) -> Type {
+    Type::Number(Fraction::try_new(n).unwrap())
+}
+
+/// Errors of the checker for each form, that is checked before it runs
+fn check(files: &[(&str, &str)], code: &str) -> Vec<GradiaError> {
+    let mut scope = scope(files);
+    let mut checker = Checker::for_program(&scope);
+    let mut errors = vec![];
+    for expr in read(code).unwrap() {
+        let expr = expand(&expr, &mut scope).unwrap();
+        errors.extend(checker.check(std::slice::from_ref(&expr)));
+        let _ = expr.eval(&mut scope);
+    }
+    errors
+}
+
+const GEO: (&str, &str) = (
+    "geo",
+    "(defrecord point x:number y:number)
+     (define (norm p:point) (+ (point-x p) (point-y p)))
+     (define (inc x:number) (+ x 1))
+     (defmacro twice (x) `(* 2 ,x))
+     (export point make-point norm inc twice)",
+);
+
+#[test]
+fn imported_values_are_bound() {
+    let code = "(import \"geo\") (norm (make-point 1 2))";
+    assert_eq!(run(&[GEO], code).unwrap(), number(3.0));
+    let code = "(import \"geo\" :as geo) (geo/norm (geo/make-point 1 2))";
+    assert_eq!(run(&[GEO], code).unwrap(), number(3.0));
+    let code = "(import \"geo\" :only (inc)) (norm 1)";
+    assert!(run(&[GEO], code).is_err());
+}
+
+#[test]
+fn imported_macro_is_expanded() {
+    assert_eq!(
+        run(&[GEO], "(import \"geo\") (twice 4)").unwrap(),
+        number(8.0)
+    );
+    assert_eq!(
+        run(&[GEO], "(import \"geo\" :as geo) (geo/twice 4)").unwrap(),
+        number(8.0)
+    );
+}
+
+#[test]
+fn import_runs_only_when_evaluated() {
+    let files = [("fail", "(raise \"loaded\")")];
+    let mut scope = scope(&files);
+    let import = &read("(import \"fail\")").unwrap()[0];
+    let expanded = expand(import, &mut scope).unwrap();
+    assert!(expanded.eval(&mut scope).is_err());
+    assert_eq!(
+        run(&files, "(if false (import \"fail\") 1)").unwrap(),
+        number(1.0)
+    );
+}
+
+#[test]
+fn import_cycle_is_error() {
+    let files = [
+        ("a", "(import \"b\") (define a 1) (export a)"),
+        ("b", "(import \"a\") (define b 2) (export b)"),
+    ];
+    let err = run(&files, "(import \"a\")").unwrap_err();
+    assert!(format!("{err}").contains("there's import cycle `a` -> `b` -> `a`"));
+}
+
+#[test]
+fn imported_record_is_annotation() {
+    let code = "(import \"geo\" :as geo)
+                (define (first p:geo/point) (geo/norm p))
+                (first (geo/make-point 1 2))";
+    assert_eq!(run(&[GEO], code).unwrap(), number(3.0));
+    assert!(check(&[GEO], code).is_empty());
+
+    // Record of another module with the same name is another type
+    let code = "(import \"geo\") (defrecord point x y) (norm (make-point 1 2))";
+    assert!(matches!(run(&[GEO], code), Err(GradiaError::Type(_, _, _))));
+}
+
+#[test]
+fn imported_signature_is_checked() {
+    let errors = check(&[GEO], "(import \"geo\") (inc \"one\")");
+    assert!(matches!(errors.as_slice(), [GradiaError::Type(_, _, _)]));
+    let errors = check(&[GEO], "(import \"geo\" :as geo) (geo/norm 1)");
+    assert!(matches!(errors.as_slice(), [GradiaError::Type(_, _, _)]));
+}
+
+#[test]
+fn error_in_module_is_reported_with_its_source() {
+    let files = [("lib", "(define (broken)\n  (missing 1))\n(export broken)")];
+    let code = "(import \"lib\")\n(broken)";
+    let err = run(&files, code).unwrap_err();
+    let report = err.report("main", code);
+    assert!(report.starts_with("lib:2:"), "{report}");
+    assert!(report.contains("(missing 1)"), "{report}");
+}
+
+#[test]
+fn error_of_module_checker_is_reported_with_its_source() {
+    let files = [(
+        "lib",
+        "(define (bad)\n  (define x:number \"s\")\n  x)\n(export bad)",
+    )];
+    let code = "(import \"lib\")";
+    let errors = check(&files, code);
+    let [err @ GradiaError::Type(_, _, _)] = errors.as_slice() else {
+        panic!("{errors:?}");
+    };
+    let report = err.report("main", code);
+    assert!(report.starts_with("lib:2:"), "{report}");
+}
+
+#[test]
+fn import_cycle_is_reported_once() {
+    let files = [
+        ("a", "(import \"b\") (define a 1) (export a)"),
+        ("b", "(import \"a\") (define b 2) (export b)"),
+    ];
+    let code = "(import \"a\")";
+    let report = run(&files, code).unwrap_err().report("main", code);
+    assert_eq!(report.matches("Error!").count(), 1, "{report}");
+    assert!(report.starts_with("b:1:9:"), "{report}");
+    assert!(!report.contains("failed to import"), "{report}");
+}
@@ -0,0 +1,117 @@
+use gradia_core::{expr::GradiaError, fraction::Fraction, types::Type};
+
+mod common;
+
+use common::{display, run};
+
+#[test]
+fn non_finite_float_has_no_fraction() {
+    assert!(Fraction::try_new(f64::NAN).is_none());
+    assert!(Fraction::try_new(f64::INFINITY).is_none());
+    assert_eq!(Fraction::try_new(0.5), Fraction::from("1/2".to_string()));
+}
+
+#[test]
+fn infinity_is_error_when_it_becomes_exact() {
+    let code = "(inexact->exact (/ (exact->inexact 1) 0))";
+    assert!(matches!(run(code), Err(GradiaError::Runtime(_, _))));
+    let code = "(range (/ (exact->inexact 1) 0))";
+    assert!(matches!(run(code), Err(GradiaError::Runtime(_, _))));
+}
+
+#[test]
+fn non_finite_atom_is_symbol() {
+    assert_eq!(display("'inf"), "inf");
+    assert_eq!(display("'NaN"), "NaN");
+}
+
+#[test]
+fn division_by_zero_is_error() {
+    assert!(matches!(run("(/ 1 0)"), Err(GradiaError::Runtime(_, _))));
+    assert!(matches!(run("(% 5 0)"), Err(GradiaError::Runtime(_, _))));
+    assert!(matches!(
+        run("(/ 1 1/2 0)"),
+        Err(GradiaError::Runtime(_, _))
+    ));
+}
+
+#[test]
+fn range_is_exact() {
+    assert_eq!(display("(range 0 1 1/3)"), "'(0 1/3 2/3)");
+    assert_eq!(display("(range 0 1/3 1/10)"), "'(0 1/10 1/5 3/10)");
+    assert_eq!(display("(range 1 0 -1/2)"), "'(1 1/2)");
+    assert!(matches!(
+        run("(range 0 1 0)"),
+        Err(GradiaError::Runtime(_, _))
+    ));
+}
+
+#[test]
+fn overflow_is_promoted_to_big_number() {
+    assert_eq!(
+        display("(* 99999999999 99999999999)"),
+        "9999999999800000000001"
+    );
+    assert_eq!(display("(+ 9223372036854775807 1)"), "9223372036854775808");
+    assert_eq!(
+        display("(- -9223372036854775808 1)"),
+        "-9223372036854775809"
+    );
+    assert_eq!(
+        display("(* -1 -9223372036854775808)"),
+        "9223372036854775808"
+    );
+    assert_eq!(
+        display("(/ -9223372036854775808 -1)"),
+        "9223372036854775808"
+    );
+    assert_eq!(
+        display("(/ 1 -9223372036854775808)"),
+        "-1/9223372036854775808"
+    );
+}
+
+#[test]
+fn big_number_is_demoted_when_it_fits() {
+    let small = |code: &str| match run(code).unwrap() {
+        Type::Number(number) => number.to_i32(),
+        other => panic!("{other:?}"),
+    };
+    assert_eq!(
+        small("(- (* 99999999999 99999999999) 9999999999800000000000)"),
+        Some(1)
+    );
+    assert_eq!(
+        small("(/ (* 99999999999 99999999999) 9999999999800000000001)"),
+        Some(1)
+    );
+    assert_eq!(
+        small("(- (+ 9223372036854775807 1) 9223372036854775806)"),
+        Some(2)
+    );
+    assert_eq!(
+        display("(- (+ 9223372036854775807 1) 1)"),
+        "9223372036854775807"
+    );
+    assert_eq!(
+        display("(+ (- -9223372036854775808 1) 1)"),
+        "-9223372036854775808"
+    );
+}
+
+#[test]
+fn power_is_exact_until_its_limit() {
+    assert_eq!(display("(^ 2 64)"), "18446744073709551616");
+    assert_eq!(display("(^ 2 -3)"), "1/8");
+    assert_eq!(display("(^ 1 100000000)"), "1");
+    assert_eq!(display("(^ -1 100000001)"), "-1");
+    assert!(matches!(
+        run("(^ 2 100000000)"),
+        Err(GradiaError::Runtime(_, _))
+    ));
+    assert!(matches!(
+        run("(^ 1/3 -100000000)"),
+        Err(GradiaError::Runtime(_, _))
+    ));
+    assert!(matches!(run("(^ 0 -1)"), Err(GradiaError::Runtime(_, _))));
+}
@@ -0,0 +1,68 @@
+use gradia_core::expr::GradiaError;
+
+mod common;
+
+use common::{display, run};
+
+#[test]
+fn optional_parameter_has_default() {
+    assert_eq!(
+        display("(define (f a &optional (b 10)) (+ a b)) (f 1)"),
+        "11"
+    );
+    assert_eq!(
+        display("(define (f a &optional (b 10)) (+ a b)) (f 1 2)"),
+        "3"
+    );
+    assert!(run("(define (f a &optional (b 10)) b) (f 1 2 3)").is_err());
+}
+
+#[test]
+fn rest_parameter_takes_extra_arguments() {
+    assert_eq!(display("(define (f a &rest xs) xs) (f 1 2 3)"), "'(2 3)");
+    assert_eq!(display("(define (f a &rest xs) xs) (f 1)"), "'()");
+    assert!(matches!(
+        run("(define (f a &rest xs) xs) (f)"),
+        Err(GradiaError::Function(0, 1, _))
+    ));
+}
+
+#[test]
+fn keyword_parameter_is_given_by_name() {
+    let code = "(define (f &key (x 1) (y 2)) (- x y))";
+    assert_eq!(display(&format!("{code} (f :y 10)")), "-9");
+    assert_eq!(display(&format!("{code} (f)")), "-1");
+    assert!(run(&format!("{code} (f :z 1)")).is_err());
+}
+
+#[test]
+fn keyword_is_taken_after_positional_arguments() {
+    assert_eq!(display("(define (f a &key (b 1)) a) (f :x)"), ":x");
+    let code = "(define (f a &optional (b 0) &key (c 1)) `(,a ,b ,c))";
+    assert_eq!(display(&format!("{code} (f :a :b :c 2)")), "'(:a :b 2)");
+    assert_eq!(display(&format!("{code} (f 1)")), "'(1 0 1)");
+}
+
+#[test]
+fn annotation_of_parameter_is_checked() {
+    let code = "(define (f &optional (x:number 1)) x) (f \"one\")";
+    assert!(matches!(run(code), Err(GradiaError::Type(_, _, _))));
+}
+
+#[test]
+fn function_with_sections_conforms_to_signature() {
+    let apply = "(define (apply f:(number number)->number) (f 1 2))";
+    // Markers aren't counted as parameters
+    let code = format!("{apply} (apply (lambda (a &optional (b 0)) (+ a b)))");
+    assert_eq!(display(&code), "3");
+    let code = format!("{apply} (apply (lambda (a b &optional (c 10)) (+ a b c)))");
+    assert_eq!(display(&code), "13");
+    let code = format!("{apply} (apply (lambda (&rest xs) (len xs)))");
+    assert_eq!(display(&code), "2");
+    let code = format!("{apply} (apply (lambda (a &key (k 5)) (+ a k)))");
+    assert!(matches!(run(&code), Err(GradiaError::Type(_, _, _))));
+    let code = format!("{apply} (apply (lambda (a b c) a))");
+    assert!(matches!(run(&code), Err(GradiaError::Type(_, _, _))));
+    let code = format!("{apply} (apply (lambda (a &optional (b:string \"\")) a))");
+    assert!(matches!(run(&code), Err(GradiaError::Type(_, _, _))));
+}
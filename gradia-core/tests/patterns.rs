@@ -0,0 +1,83 @@
+use gradia_core::{expr::GradiaError, parser::read, typecheck::check};
+
+mod common;
+
+use common::{display, run};
+
+#[test]
+fn quoted_header_defines_function() {
+    assert_eq!(display("(define '(add x y) (+ x y)) (add 1 2)"), "3");
+    assert_eq!(display("(define (add x y) (+ x y)) (add 1 2)"), "3");
+    assert!(check(&read("(define '(add x:number) x) (add \"one\")").unwrap()).len() == 1);
+}
+
+#[test]
+fn define_values_destructures_list() {
+    let code = "(define-values (a b &rest c) '(1 2 3 4)) (+ a b (len c))";
+    assert_eq!(display(code), "5");
+    let code = "(define-values (a (b c)) '(1 (2 3))) (+ a b c)";
+    assert_eq!(display(code), "6");
+    assert!(matches!(
+        run("(define-values (a b) '(1))"),
+        Err(GradiaError::Runtime(_, _))
+    ));
+    assert!(matches!(
+        run("(define-values a '(1))"),
+        Err(GradiaError::Syntax(_, _))
+    ));
+}
+
+#[test]
+fn match_takes_first_matching_clause() {
+    let code = "(define (f x)
+                  (match x
+                    (0 'zero)
+                    (n:number :when (> n 0) 'positive)
+                    (n:number 'negative)
+                    ((a b) 'pair)
+                    (:none 'none)
+                    (_ 'other)))";
+    for (value, expected) in [
+        ("0", "zero"),
+        ("5", "positive"),
+        ("-5", "negative"),
+        ("'(1 2)", "pair"),
+        (":none", "none"),
+        ("\"x\"", "other"),
+    ] {
+        assert_eq!(display(&format!("{code} (f {value})")), expected);
+    }
+}
+
+#[test]
+fn pattern_failures() {
+    // No clause matches the value
+    assert!(matches!(
+        run("(match 1 (\"one\" 1))"),
+        Err(GradiaError::Runtime(_, _))
+    ));
+    // List of other length doesn't match
+    assert_eq!(
+        display("(match '(1 2 3) ((a b) 'two) ((a &rest b) b))"),
+        "'(2 3)"
+    );
+    // Annotation that the value doesn't have makes the clause fail
+    assert_eq!(
+        display("(match \"x\" (n:number n) (s:string 'string))"),
+        "string"
+    );
+    // `&rest` needs exactly one pattern after it
+    assert!(matches!(
+        run("(match '(1 2) ((a &rest) a))"),
+        Err(GradiaError::Syntax(_, _))
+    ));
+    // Destructuring parameter fails when the argument doesn't match
+    assert!(matches!(
+        run("(define (f (a b)) a) (f '(1))"),
+        Err(GradiaError::Runtime(_, _))
+    ));
+    assert!(matches!(
+        run("(let (((a b) '(1 2 3))) a)"),
+        Err(GradiaError::Runtime(_, _))
+    ));
+}
@@ -0,0 +1,35 @@
+use gradia_core::expr::GradiaError;
+
+mod common;
+
+use common::run;
+
+#[test]
+fn record_annotation_is_checked() {
+    let code = "(defrecord point x:number y:number)
+                (define (norm p:point) (+ (point-x p) (point-y p)))
+                (norm (make-point 1 2))";
+    assert_eq!(run(code).unwrap(), run("3").unwrap());
+    let code = "(defrecord point x y)
+                (define (norm p:point) p)
+                (norm 1)";
+    assert!(matches!(run(code), Err(GradiaError::Type(_, _, _))));
+}
+
+#[test]
+fn unknown_annotation_is_syntax_error() {
+    let code = "(define (twice x:nubmer) (* x 2)) (twice 1)";
+    assert!(matches!(run(code), Err(GradiaError::Syntax(_, _))));
+    let code = "(define (f x:nubmer|string) x) (f 1)";
+    assert!(matches!(run(code), Err(GradiaError::Syntax(_, _))));
+}
+
+#[test]
+fn records_of_same_name_are_different() {
+    // Record defined inside of the function is another type than the one outside
+    let code = "(defrecord point x y)
+                (define (local) (defrecord point x y) (make-point 1 2))
+                (define (first p:point) (point-x p))
+                (first (local))";
+    assert!(matches!(run(code), Err(GradiaError::Type(_, _, _))));
+}
@@ -0,0 +1,40 @@
+use gradia_core::{expr::GradiaError, parser::read_recovering, types::Type};
+
+fn messages(errors: &[GradiaError]) -> Vec<String> {
+    errors.iter().map(|i| i.to_string()).collect()
+}
+
+#[test]
+fn all_syntax_errors_are_reported() {
+    let code = "(+ 1 2))\n(print \"open\n";
+    let (program, errors) = read_recovering(code);
+    assert_eq!(program.len(), 2);
+    assert_eq!(errors.len(), 3, "{:?}", messages(&errors));
+    assert!(errors
+        .iter()
+        .all(|i| matches!(i, GradiaError::Syntax(_, Some(_)))));
+}
+
+#[test]
+fn unclosed_parentheses_are_reported_at_opening() {
+    let (program, errors) = read_recovering("(define x 1)\n(define (f x)\n  (+ x 1)");
+    assert_eq!(program.len(), 2);
+    let [GradiaError::Syntax(_, Some(span))] = errors.as_slice() else {
+        panic!("{:?}", messages(&errors));
+    };
+    assert_eq!((span.line, span.column), (2, 1));
+}
+
+#[test]
+fn mismatched_parentheses_are_reported() {
+    let (_, errors) = read_recovering("(list 1 2}");
+    assert_eq!(errors.len(), 1, "{:?}", messages(&errors));
+}
+
+#[test]
+fn bad_annotation_keeps_the_rest() {
+    let (program, errors) = read_recovering("x:list<> (+ 1 2)");
+    assert_eq!(program.len(), 2);
+    assert_eq!(errors.len(), 1, "{:?}", messages(&errors));
+    assert!(matches!(program[1].expr, Type::Expr(_)));
+}
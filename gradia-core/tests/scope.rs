@@ -0,0 +1,134 @@
+use gradia_core::{
+    expr::GradiaError,
+    parser::{parse, read},
+    std::builtin_function,
+    types::{Function, Native, Type},
+};
+use std::rc::Rc;
+
+mod common;
+
+use common::{display, run};
+
+#[test]
+fn set_updates_outer_variable_from_loop() {
+    let code = "
+        (define total 0)
+        (for (range 5) (lambda (i) (set! total (+ total i))))
+        total";
+    assert_eq!(display(code), "10");
+}
+
+#[test]
+fn set_updates_nearest_binding() {
+    let code = "
+        (define x 1)
+        (define (shadow x) (set! x 10) x)
+        (concat (shadow 2) x)";
+    assert_eq!(display(code), "\"101\"");
+}
+
+#[test]
+fn set_without_binding_is_error() {
+    assert!(matches!(
+        run("(set! undefined 1)"),
+        Err(GradiaError::Runtime(_, _))
+    ));
+}
+
+#[test]
+fn set_checks_annotation() {
+    assert!(matches!(
+        run("(define x 1) (set! x:number \"a\")"),
+        Err(GradiaError::Type(_, _, _))
+    ));
+}
+
+#[test]
+fn counter_closure_keeps_its_state() {
+    let code = "
+        (define (make-counter)
+          (let ((count 0))
+            (lambda () (set! count (+ count 1)) count)))
+        (define counter (make-counter))
+        (counter)
+        (counter)
+        (counter)";
+    assert_eq!(display(code), "3");
+}
+
+#[test]
+fn let_shadows_without_touching_outer_variable() {
+    let code = "
+        (define x 1)
+        (define inner (let ((x 2)) (set! x 3) x))
+        (concat inner x)";
+    assert_eq!(display(code), "\"31\"");
+}
+
+#[test]
+fn let_evaluates_values_in_outer_scope() {
+    let code = "
+        (define x 1)
+        (let ((x 10) (y x)) (+ x y))";
+    assert_eq!(display(code), "11");
+}
+
+#[test]
+fn let_star_sees_previous_bindings() {
+    let code = "
+        (define x 1)
+        (let* ((x 10) (y x)) (+ x y))";
+    assert_eq!(display(code), "20");
+}
+
+#[test]
+fn define_in_function_does_not_leak() {
+    let code = "
+        (define x 1)
+        (define (f) (define x 2) x)
+        (concat (f) x)";
+    assert_eq!(display(code), "\"21\"");
+}
+
+#[test]
+fn frame_of_function_defined_inside_is_freed() {
+    let marker = Rc::new(Native {
+        name: "marker".to_string(),
+        call: Box::new(|_| Ok(Type::Null)),
+    });
+    let mut scope = builtin_function();
+    scope.insert(
+        "marker".to_string(),
+        Type::Function(Function::Native(marker.clone())),
+    );
+    // Each call keeps the marker in its frame, whose inner functions refer back to the frame
+    let code = "(define (f m) (define (g) m) (define h (lambda () (g))) (h))
+                (f marker) (f marker) (f marker)";
+    for expr in read(code).unwrap() {
+        expr.eval(&mut scope).unwrap();
+    }
+    assert_eq!(Rc::strong_count(&marker), 2);
+}
+
+#[test]
+fn function_defined_inside_outlives_the_call() {
+    let code = "(define (make n) (define (get) n) get) ((make 5))";
+    assert_eq!(display(code), "5");
+    let code = "(define (make) (define n 0) (define (next) (set! n (+ n 1)) n) next)
+                (define next (make))
+                (next) (next)";
+    assert_eq!(display(code), "2");
+}
+
+#[test]
+fn parse_reads_source_of_token() {
+    let expr = parse(("(+ 1 2)".to_string(), None)).unwrap();
+    assert_eq!(
+        format!("{:?}", expr.eval(&mut builtin_function()).unwrap()),
+        "3"
+    );
+    let expr = parse(("x".to_string(), Some("number".to_string()))).unwrap();
+    assert!(expr.annotate.is_some());
+    assert!(parse(("(+ 1".to_string(), None)).is_err());
+}
@@ -0,0 +1,45 @@
+use gradia_core::{expr::GradiaError, parser::read, types::Type};
+
+fn string(code: &str) -> String {
+    match &read(code).unwrap()[0].expr {
+        Type::String(string) => string.clone(),
+        other => panic!("`{other:?}` is not string"),
+    }
+}
+
+#[test]
+fn escape_sequences_are_replaced() {
+    assert_eq!(string(r#""say \"hi\"""#), "say \"hi\"");
+    assert_eq!(string(r#""a\\b""#), "a\\b");
+    assert_eq!(string(r#""line\nnext\ttab""#), "line\nnext\ttab");
+    assert_eq!(string(r#""\u{3042}\u{1F600}""#), "あ😀");
+}
+
+#[test]
+fn invalid_escape_is_syntax_error() {
+    for code in [r#""\q""#, r#""\u{110000}""#, r#""\u3042""#] {
+        assert!(
+            matches!(read(code), Err(GradiaError::Syntax(_, Some(_)))),
+            "{code}"
+        );
+    }
+    assert!(matches!(
+        read("\"open"),
+        Err(GradiaError::Syntax(_, Some(_)))
+    ));
+}
+
+#[test]
+fn raw_string_has_no_escape() {
+    assert_eq!(string(r#"r"C:\path\n""#), "C:\\path\\n");
+    assert_eq!(string(r##"r#"say "hi""#"##), "say \"hi\"");
+    assert!(matches!(read("r#\"open\""), Err(GradiaError::Syntax(_, _))));
+}
+
+#[test]
+fn printed_string_is_read_back() {
+    for value in ["quote \" and \\ backslash", "new\nline\ttab", "あ", ""] {
+        let printed = format!("{:?}", Type::String(value.to_string()));
+        assert_eq!(string(&printed), value);
+    }
+}
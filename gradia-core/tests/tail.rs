@@ -0,0 +1,62 @@
+use gradia_core::expr::GradiaError;
+
+mod common;
+
+use common::{display, run};
+
+#[test]
+fn tail_call_in_if_runs_on_flat_stack() {
+    let code = "(define (loop n acc) (if (= n 0) acc (loop (- n 1) (+ acc 1))))
+                (loop 100000 0)";
+    assert_eq!(display(code), "100000");
+}
+
+#[test]
+fn tail_call_in_cond_runs_on_flat_stack() {
+    let code = "(define (loop n)
+                  (cond ((= n 0) 'done)
+                        (true (loop (- n 1)))))
+                (loop 100000)";
+    assert_eq!(display(code), "done");
+}
+
+#[test]
+fn tail_call_in_let_runs_on_flat_stack() {
+    let code = "(define (loop n)
+                  (let ((m (- n 1)))
+                    (if (< m 0) 'done (loop m))))
+                (loop 100000)";
+    assert_eq!(display(code), "done");
+}
+
+#[test]
+fn tail_call_in_match_runs_on_flat_stack() {
+    let code = "(define (loop n)
+                  (match n
+                    (0 'done)
+                    (_ (loop (- n 1)))))
+                (loop 100000)";
+    assert_eq!(display(code), "done");
+}
+
+#[test]
+fn mutual_recursion_runs_on_flat_stack() {
+    let code = "(define (ev n) (if (= n 0) true (od (- n 1))))
+                (define (od n) (if (= n 0) false (ev (- n 1))))";
+    assert_eq!(display(&format!("{code} (ev 100000)")), "true");
+    assert_eq!(display(&format!("{code} (od 11)")), "true");
+}
+
+#[test]
+fn return_types_of_mutual_recursion_are_checked() {
+    let code = "(define (ev n):bool (if (= n 0) true (od (- n 1))))
+                (define (od n) (if (= n 0) 'odd (ev (- n 1))))";
+    assert_eq!(display(&format!("{code} (ev 10)")), "true");
+    assert!(matches!(
+        run(&format!("{code} (ev 11)")),
+        Err(GradiaError::Type(_, _, _))
+    ));
+    let code = "(define (ev n):bool (if (= n 0) true (od (- n 1))))
+                (define (od n):any (if (= n 0) false (ev (- n 1))))";
+    assert_eq!(display(&format!("{code} (od 100001)")), "true");
+}
@@ -0,0 +1,93 @@
+use gradia_core::{
+    expr::GradiaError,
+    parser::read,
+    typecheck::{check, Checker},
+};
+
+fn errors(code: &str) -> Vec<GradiaError> {
+    check(&read(code).unwrap())
+}
+
+/// Errors when the forms are checked one by one, like the REPL does
+fn errors_by_form(code: &str) -> Vec<GradiaError> {
+    let mut checker = Checker::new();
+    read(code)
+        .unwrap()
+        .iter()
+        .flat_map(|i| checker.check(std::slice::from_ref(i)))
+        .collect()
+}
+
+#[test]
+fn literal_conflicts_with_annotation() {
+    assert!(matches!(
+        errors("(+ \"a\":number 1)").as_slice(),
+        [GradiaError::Type(_, _, Some(_))]
+    ));
+    assert!(matches!(
+        errors("(define x:string 1)").as_slice(),
+        [GradiaError::Type(_, _, Some(_))]
+    ));
+    assert!(errors("(define x:number 1)").is_empty());
+}
+
+#[test]
+fn call_before_definition_is_checked() {
+    let code = "(define (g) (f \"a\")) (define (f n:number) n)";
+    assert!(matches!(
+        errors(code).as_slice(),
+        [GradiaError::Type(_, _, Some(_))]
+    ));
+    let code = "(define (g) (f 1 2)) (define (f n:number) n)";
+    assert!(matches!(
+        errors(code).as_slice(),
+        [GradiaError::Function(2, 1, Some(_))]
+    ));
+}
+
+#[test]
+fn set_widens_variable() {
+    let code = "(define x \"a\") (set! x 1) (define (f n:number) n) (f x)";
+    assert!(errors(code).is_empty());
+    assert!(errors_by_form(code).is_empty());
+    assert!(errors_by_form("(define x \"a\") (define (f n:number) n) (f x)").len() == 1);
+}
+
+#[test]
+fn union_overlaps_its_alternatives() {
+    assert!(errors("(define (f n:number|string) n) (f \"a\") (f 1)").is_empty());
+    assert!(matches!(
+        errors("(define (f n:number|string) n) (f true)").as_slice(),
+        [GradiaError::Type(_, _, Some(_))]
+    ));
+    let code = "(define (f x) (if x 1 \"a\")) (define (g n:number) n) (g (f true))";
+    assert!(errors(code).is_empty());
+}
+
+#[test]
+fn list_literal_is_checked_by_element() {
+    assert!(errors("(define x:list<number> '(1 2 3))").is_empty());
+    assert!(errors("(define x:list<list<string>> '((\"a\") (\"b\")))").is_empty());
+    let errors = errors("(define x:list<number> '(1 \"two\" 3 \"four\"))");
+    assert!(matches!(
+        errors.as_slice(),
+        [GradiaError::Type(_, _, Some(a)), GradiaError::Type(_, _, Some(b))]
+            if (a.column, b.column) == (28, 36)
+    ));
+}
+
+#[test]
+fn nested_list_literal_is_checked_by_element() {
+    assert!(matches!(
+        errors("(define x:list<list<string>> '((\"a\") (\"b\" 1)))").as_slice(),
+        [GradiaError::Type(_, _, Some(_))]
+    ));
+    assert!(matches!(
+        errors("(define x:list<list<string>> '((\"a\") \"b\"))").as_slice(),
+        [GradiaError::Type(_, _, Some(_))]
+    ));
+    assert!(matches!(
+        errors("(define (f xs:list<list<number>>) xs) (f '((1) (\"a\")))").as_slice(),
+        [GradiaError::Type(_, _, Some(_))]
+    ));
+}
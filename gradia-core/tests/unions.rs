@@ -0,0 +1,94 @@
+use gradia_core::{expr::GradiaError, types::Class};
+
+mod common;
+
+use common::{display, run};
+
+/// Name of the annotation as it's parsed, or None for any
+fn parse(source: &str) -> Option<String> {
+    Class::from(source.to_string())
+        .unwrap()
+        .map(|i| i.get_type())
+}
+
+#[test]
+fn union_is_split_at_top_level() {
+    assert_eq!(parse("number|string").unwrap(), "number|string");
+    assert_eq!(
+        parse("list<number|string>|null").unwrap(),
+        "list<number|string>?"
+    );
+    assert_eq!(parse("number|number|string").unwrap(), "number|string");
+    assert_eq!(parse("number|any"), None);
+}
+
+#[test]
+fn arrow_of_signature_does_not_close_angle() {
+    assert_eq!(
+        parse("list<(number)->number>|string").unwrap(),
+        "list<(number)->number>|string"
+    );
+    assert_eq!(
+        parse("(list<number>)->string|null").unwrap(),
+        "(list<number>)->string?"
+    );
+}
+
+#[test]
+fn optional_is_union_with_null() {
+    assert_eq!(parse("number?").unwrap(), "number?");
+    assert_eq!(parse("list<number>?").unwrap(), "list<number>?");
+    assert_eq!(parse("number|null").unwrap(), "number?");
+    assert_eq!(parse("any?"), None);
+}
+
+#[test]
+fn malformed_union_is_syntax_error() {
+    for source in ["number|", "|string", "list<number", "number|nope!"] {
+        assert!(
+            matches!(
+                Class::from(source.to_string()),
+                Err(GradiaError::Syntax(_, _))
+            ),
+            "{source}"
+        );
+    }
+}
+
+#[test]
+fn union_is_described_with_its_alternatives() {
+    let describe = |source: &str| Class::from(source.to_string()).unwrap().unwrap().describe();
+    assert_eq!(describe("number"), "type `number`");
+    assert_eq!(describe("number?"), "types `number` or `null`");
+    assert_eq!(
+        describe("number|string|bool"),
+        "types `number`, `string` or `bool`"
+    );
+    assert_eq!(describe("(number)->number?"), "type `(number)->number?`");
+}
+
+#[test]
+fn value_takes_first_matching_alternative() {
+    assert_eq!(display("(define x:number|string \"a\") x"), "\"a\"");
+    assert_eq!(display("(define x:string? null) x"), "null");
+    let err = run("(define x:number|string true)").unwrap_err();
+    assert!(
+        matches!(&err, GradiaError::Type(_, expected, _) if expected == "types `number` or `string`"),
+        "{err:?}"
+    );
+}
+
+#[test]
+fn unknown_record_in_union_is_syntax_error() {
+    // Syntax error of the alternative stops the check instead of trying the next one
+    assert!(matches!(
+        run("(define x:point|string \"a\")"),
+        Err(GradiaError::Syntax(_, _))
+    ));
+    assert!(matches!(
+        run("(define x:number|point \"a\")"),
+        Err(GradiaError::Syntax(_, _))
+    ));
+    // Alternative before the unknown one that matches is used
+    assert_eq!(display("(define x:number|point 1) x"), "1");
+}
@@ -1,27 +1,50 @@
 use gradia_core::{
-    parser::{parse, tokenize},
+    expr::GradiaError,
+    macros::expand,
+    module::{program, Loader},
+    parser::read_recovering,
     std::builtin_function,
-    types::{Function, Type},
+    typecheck::Checker,
+    types::{Function, Scope, Type},
 };
+use std::cell::RefCell;
 use std::collections::HashMap;
+use std::rc::Rc;
 use wasm_bindgen::prelude::*;
 
 #[wasm_bindgen]
 pub struct Gradia {
-    scope: HashMap<String, Type>,
+    scope: Scope,
+    checker: Checker,
+    sources: Sources,
+}
+
+/// Modules given by `add_module` in place of files, as there's no file system
+#[derive(Clone, Default)]
+struct Sources(Rc<RefCell<HashMap<String, String>>>);
+
+impl Loader for Sources {
+    fn load(&self, path: &str) -> Result<String, String> {
+        self.0
+            .borrow()
+            .get(path)
+            .cloned()
+            .ok_or_else(|| "there's no such module".to_string())
+    }
 }
 
 #[wasm_bindgen]
 impl Gradia {
     #[wasm_bindgen(constructor)]
     pub fn new() -> Gradia {
-        let mut scope = builtin_function();
+        let scope = builtin_function();
         scope.insert("stdout".to_string(), Type::String(String::new()));
         scope.insert(
             "print".to_string(),
             Type::Function(Function::BuiltIn(|params, scope| {
-                scope.insert(
-                    "stdout".to_string(),
+                // Output is collected in the global variable even when called inside of functions
+                scope.set(
+                    "stdout",
                     Type::String(
                         scope.get("stdout").unwrap().get_string()
                             + &params
@@ -34,7 +57,18 @@ impl Gradia {
                 Result::Ok(Type::Null)
             })),
         );
-        Gradia { scope }
+        let sources = Sources::default();
+        let scope = program(scope, sources.clone(), None);
+        Gradia {
+            checker: Checker::for_program(&scope),
+            scope,
+            sources,
+        }
+    }
+
+    /// Register the code that can be imported by the path
+    pub fn add_module(&mut self, path: String, code: String) {
+        self.sources.0.borrow_mut().insert(path, code);
     }
 
     pub fn run(&mut self, code: String) {
@@ -42,23 +76,29 @@ impl Gradia {
     }
 
     pub fn eval(&mut self, code: String) -> String {
+        let (program, errors) = read_recovering(&code);
+        if !errors.is_empty() {
+            return report(&errors, &code);
+        }
         let mut result = String::new();
-        match tokenize(code) {
-            Ok(lines) => {
-                for line in lines {
-                    match parse(line) {
-                        Ok(ast) => {
-                            result = match ast.eval(&mut self.scope) {
-                                Ok(value) => format!("{:?}", value),
-                                Err(err) => format!("{}", err),
-                            }
-                        }
-                        Err(err) => result = format!("{}", err),
-                    }
+        for ast in program {
+            let ast = match expand(&ast, &mut self.scope) {
+                Ok(ast) => ast,
+                Err(err) => {
+                    result = err.report("<input>", &code);
+                    continue;
                 }
+            };
+            let errors = self.checker.check(std::slice::from_ref(&ast));
+            if !errors.is_empty() {
+                result = report(&errors, &code);
+                continue;
             }
-            Err(err) => result = format!("{}", err),
-        };
+            result = match ast.eval(&mut self.scope) {
+                Ok(value) => format!("{:?}", value),
+                Err(err) => err.report("<input>", &code),
+            }
+        }
         result
     }
 
@@ -67,9 +107,29 @@ impl Gradia {
     }
 }
 
+impl Default for Gradia {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 #[wasm_bindgen]
 pub fn run_gradia(code: String) -> String {
     let mut gradia = Gradia::new();
     gradia.run(code);
     gradia.get_stdout()
 }
+
+/// Report all syntax errors of the code for editors, it's empty when there's no error
+#[wasm_bindgen]
+pub fn diagnose(code: String) -> String {
+    report(&read_recovering(&code).1, &code)
+}
+
+fn report(errors: &[GradiaError], code: &str) -> String {
+    errors
+        .iter()
+        .map(|err| err.report("<input>", code))
+        .collect::<Vec<String>>()
+        .join("\n")
+}